@@ -11,6 +11,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // build.rs. So, export it to crate compilation as well.
     let profile = env::var("PROFILE").unwrap();
     println!("cargo:rustc-env=PROFILE={profile}");
+    // The shaders are built with the runner's toolchain, so its version is
+    // part of the SPIR-V cache keys
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let version = std::process::Command::new(rustc).arg("-V").output()?.stdout;
+    let version = String::from_utf8(version)?;
+    println!("cargo:rustc-env=RUSTC_VERSION={}", version.trim());
     if target_arch != "wasm32" {
         return Ok(());
     }
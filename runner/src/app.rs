@@ -1,34 +1,40 @@
 use crate::{
-    shader::{maybe_watch, CompiledShaderModules},
     state,
     window::{UserEvent, Window},
     Options,
 };
 
 use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, StartCause, VirtualKeyCode, WindowEvent},
     event_loop::ControlFlow,
 };
 
-async fn run(options: Options, window: Window, compiled_shader_modules: CompiledShaderModules) {
-    let mut app = state::State::new(&window, compiled_shader_modules, options).await;
+async fn run(options: Options, window: Window) {
+    let mut app = state::State::new(&window, options).await;
 
     window.event_loop.run(move |event, _, control_flow| {
         let window = &window.window;
-        *control_flow = ControlFlow::Wait;
+        // Without a device, frames wait for the next attempt to recreate it
+        *control_flow = app.recovery_wait().unwrap_or(ControlFlow::Wait);
 
         match event {
             Event::RedrawRequested(window_id) if window_id == window.id() => {
-                // TODO: only redraw if needed?
-                window.request_redraw();
+                app.update_and_render(window);
 
-                if let Err(wgpu::SurfaceError::OutOfMemory) = app.update_and_render(&window) {
-                    *control_flow = ControlFlow::Exit
+                // TODO: only redraw if needed?
+                if !is_minimized(window) && app.recovery_wait().is_none() {
+                    window.request_redraw();
                 }
             }
-            Event::MainEventsCleared => {
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
                 window.request_redraw();
             }
+            Event::MainEventsCleared => {
+                // Minimized windows wait for the `Resized` event that restores them
+                if !is_minimized(window) && app.recovery_wait().is_none() {
+                    window.request_redraw();
+                }
+            }
             Event::WindowEvent { event, window_id }
                 if window_id == window.id() && !app.ui_consumes_event(&event) =>
             {
@@ -43,33 +49,90 @@ async fn run(options: Options, window: Window, compiled_shader_modules: Compiled
                             },
                         ..
                     } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(size) => app.resize(size),
+                    WindowEvent::Resized(size) => {
+                        app.resize(size);
+                        window.request_redraw();
+                    }
                     WindowEvent::MouseInput { state, button, .. } => app.mouse_input(state, button),
                     WindowEvent::MouseWheel { delta, .. } => app.mouse_scroll(delta),
                     WindowEvent::CursorMoved { position, .. } => app.mouse_move(position),
+                    WindowEvent::KeyboardInput { input, .. } => app.keyboard_input(input),
+                    WindowEvent::Touch(touch) => app.touch(touch),
+                    WindowEvent::TouchpadMagnify { delta, .. } => app.touchpad_magnify(delta),
+                    WindowEvent::TouchpadRotate { delta, .. } => app.touchpad_rotate(delta),
+                    WindowEvent::ModifiersChanged(modifiers) => app.modifiers_changed(modifiers),
+                    WindowEvent::Focused(false) => app.focus_lost(),
                     _ => {}
                 }
             }
             Event::UserEvent(event) => match event {
+                UserEvent::CompileStarted(shader) => {
+                    app.compile_started(shader);
+                }
                 UserEvent::NewModule(shader, new_module) => {
                     app.new_module(shader, new_module);
                     window.request_redraw();
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                UserEvent::CompileFailed(shader, diagnostics) => {
+                    app.compile_failed(shader, diagnostics);
+                }
+                UserEvent::WatchFailed(err) => {
+                    app.watch_failed(err);
+                }
                 UserEvent::SwitchShader(shader) => {
                     app.switch_shader(shader);
                 }
-                UserEvent::SetVSync(enable) => {
-                    app.set_vsync(enable);
+                UserEvent::SetPresentMode(mode) => {
+                    app.set_present_mode(mode);
+                }
+                UserEvent::SetMsaa(sample_count) => {
+                    app.set_msaa(sample_count);
+                }
+                UserEvent::SetSupersampling(factor) => {
+                    app.set_supersampling(factor);
+                }
+                UserEvent::SetRenderScale(percent) => {
+                    app.set_render_scale(percent);
+                }
+                UserEvent::SetUpscaling(upscaling) => {
+                    app.set_upscaling(upscaling);
+                }
+                UserEvent::SetTonemapping(tonemapping) => {
+                    app.set_tonemapping(tonemapping);
+                }
+                UserEvent::SetAccumulate(accumulate) => {
+                    app.set_accumulate(accumulate);
                 }
                 UserEvent::NewVerticesReady => {
                     app.new_vertices();
                 }
+                UserEvent::NewTexturesReady => {
+                    app.new_textures();
+                }
+                UserEvent::SetPaused(paused) => {
+                    app.clock().set_paused(paused);
+                }
+                UserEvent::SetSpeed(speed) => {
+                    app.clock().set_speed(speed);
+                }
+                UserEvent::SeekTime(time) => {
+                    app.clock().seek(time);
+                }
+                UserEvent::StepFrames(frames) => {
+                    app.clock().step(frames);
+                }
             },
             _ => {}
         }
     });
 }
 
+fn is_minimized(window: &winit::window::Window) -> bool {
+    let size = window.inner_size();
+    size.width == 0 || size.height == 0
+}
+
 pub fn start(options: Options) {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
@@ -82,22 +145,6 @@ pub fn start(options: Options) {
 
     let window = Window::new();
 
-    // Build the shader before we pop open a window, since it might take a while.
-    let initial_shader = maybe_watch(
-        &options,
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            let proxy = window.event_loop.create_proxy();
-            Some(Box::new(move |res| {
-                match proxy.send_event(UserEvent::NewModule(options.shader, res)) {
-                    Ok(it) => it,
-                    // ShaderModuleDescriptor is not `Debug`, so can't use unwrap/expect
-                    Err(_err) => panic!("Event loop dead"),
-                }
-            }))
-        },
-    );
-
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             use winit::platform::web::WindowExtWebSys;
@@ -113,13 +160,11 @@ pub fn start(options: Options) {
             wasm_bindgen_futures::spawn_local(run(
                 options.clone(),
                 window,
-                initial_shader,
             ));
         } else {
             futures::executor::block_on(run(
                 options.clone(),
                 window,
-                initial_shader,
             ));
         }
     }
@@ -0,0 +1,311 @@
+//! Uniform and storage buffers and textures that a controller binds next to
+//! its push constants, for inputs that don't fit in 128 bytes.
+//!
+//! The buffer at index `i` of [`Controller::bindings`] is bound to
+//! `descriptor_set = 0, binding = i`, for example
+//! `#[spirv(storage_buffer, descriptor_set = 0, binding = 0)] points: &[Vec2]`.
+//!
+//! The texture at index `i` of [`Controller::textures`] is bound to
+//! `descriptor_set = 1, binding = 2 * i`, and its sampler to `binding = 2 * i + 1`.
+//!
+//! [`Controller::bindings`]: crate::controller::Controller::bindings
+//! [`Controller::textures`]: crate::controller::Controller::textures
+
+use crate::{context::GraphicsContext, texture::Texture};
+use std::{num::NonZeroU64, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BufferKind {
+    Uniform,
+    Storage { read_only: bool },
+}
+
+pub struct Binding<'a> {
+    pub kind: BufferKind,
+    pub contents: &'a [u8],
+}
+
+impl<'a> Binding<'a> {
+    pub fn uniform<T: bytemuck::Pod>(value: &'a T) -> Self {
+        Self {
+            kind: BufferKind::Uniform,
+            contents: bytemuck::bytes_of(value),
+        }
+    }
+
+    /// A read-only storage buffer, which the shader sees as a slice of `values.len()` elements
+    pub fn storage<T: bytemuck::Pod>(values: &'a [T]) -> Self {
+        Self {
+            kind: BufferKind::Storage { read_only: true },
+            contents: bytemuck::cast_slice(values),
+        }
+    }
+
+    /// A storage buffer the fragment and compute shaders may write to, which
+    /// only starts out as `initial` when it is created or changes length
+    pub fn writable_storage<T: bytemuck::Pod>(initial: &'a [T]) -> Self {
+        Self {
+            kind: BufferKind::Storage { read_only: false },
+            contents: bytemuck::cast_slice(initial),
+        }
+    }
+}
+
+/// The buffers and bind group backing a controller's [`Binding`]s. Contents
+/// may change size between frames, but the kinds are fixed when the pipeline
+/// is created, so the pipeline is rebuilt when they change.
+pub struct BindGroup {
+    pub layout: wgpu::BindGroupLayout,
+    kinds: Vec<BufferKind>,
+    buffers: Vec<wgpu::Buffer>,
+    /// Bound size of each buffer, which is smaller than the buffer after a shrink
+    sizes: Vec<u64>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl BindGroup {
+    pub fn new(device: &wgpu::Device, bindings: &[Binding]) -> Self {
+        let entries = bindings
+            .iter()
+            .enumerate()
+            .map(|(i, binding)| wgpu::BindGroupLayoutEntry {
+                binding: i as u32,
+                visibility: match binding.kind {
+                    // Vertex shaders can't write to storage buffers without an extra feature
+                    BufferKind::Storage { read_only: false } => {
+                        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+                    }
+                    _ => wgpu::ShaderStages::all(),
+                },
+                ty: wgpu::BindingType::Buffer {
+                    ty: match binding.kind {
+                        BufferKind::Uniform => wgpu::BufferBindingType::Uniform,
+                        BufferKind::Storage { read_only } => {
+                            wgpu::BufferBindingType::Storage { read_only }
+                        }
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Controller Bind Group Layout"),
+            entries: &entries,
+        });
+        Self {
+            layout,
+            kinds: bindings.iter().map(|binding| binding.kind).collect(),
+            buffers: vec![],
+            sizes: vec![],
+            bind_group: None,
+        }
+    }
+
+    /// Whether `bindings` have the kinds this was created with.
+    pub fn matches(&self, bindings: &[Binding]) -> bool {
+        bindings
+            .iter()
+            .map(|binding| binding.kind)
+            .eq(self.kinds.iter().copied())
+    }
+
+    /// Copies `bindings`, which must [match](BindGroup::matches), to the GPU,
+    /// reallocating buffers that are too small. Writable storage buffers
+    /// belong to the shaders, and are only written when they are created or
+    /// change size.
+    pub fn upload(&mut self, ctx: &GraphicsContext, bindings: &[Binding]) {
+        for (i, binding) in bindings.iter().enumerate() {
+            // wgpu doesn't allow empty bindings, and copies in multiples of 4 bytes
+            let size = (binding.contents.len().max(1) as u64 + 3) & !3;
+            if self
+                .buffers
+                .get(i)
+                .map_or(true, |buffer| buffer.size() < size)
+            {
+                let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Controller Buffer"),
+                    size: size.next_power_of_two(),
+                    usage: match binding.kind {
+                        BufferKind::Uniform => wgpu::BufferUsages::UNIFORM,
+                        BufferKind::Storage { .. } => wgpu::BufferUsages::STORAGE,
+                    } | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                if i < self.buffers.len() {
+                    self.buffers[i] = buffer;
+                } else {
+                    self.buffers.push(buffer);
+                    self.sizes.push(0);
+                }
+                self.bind_group = None;
+            }
+            if self.sizes[i] != size {
+                self.sizes[i] = size;
+                self.bind_group = None;
+            } else if binding.kind == (BufferKind::Storage { read_only: false }) {
+                // Leave what the shaders wrote for the next frame
+                continue;
+            }
+            if binding.contents.len() as u64 == size {
+                ctx.queue
+                    .write_buffer(&self.buffers[i], 0, binding.contents);
+            } else {
+                let mut contents = binding.contents.to_vec();
+                contents.resize(size as usize, 0);
+                ctx.queue.write_buffer(&self.buffers[i], 0, &contents);
+            }
+        }
+        if self.bind_group.is_none() {
+            let entries = self
+                .buffers
+                .iter()
+                .zip(&self.sizes)
+                .enumerate()
+                .map(|(i, (buffer, &size))| wgpu::BindGroupEntry {
+                    binding: i as u32,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer,
+                        offset: 0,
+                        size: NonZeroU64::new(size),
+                    }),
+                })
+                .collect::<Vec<_>>();
+            self.bind_group = Some(ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Controller Bind Group"),
+                layout: &self.layout,
+                entries: &entries,
+            }));
+        }
+    }
+
+    /// Panics if called before [`BindGroup::upload`]
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        self.bind_group.as_ref().unwrap()
+    }
+}
+
+/// An image sampled by the fragment or compute shader
+pub struct TextureBinding<'a> {
+    pub image: &'a image::RgbaImage,
+    /// Colors are stored as sRGB and converted to linear when sampled, set
+    /// this to `false` for data such as lookup tables
+    pub srgb: bool,
+    pub filter: wgpu::FilterMode,
+    pub address_mode: wgpu::AddressMode,
+}
+
+impl<'a> TextureBinding<'a> {
+    pub fn new(image: &'a image::RgbaImage) -> Self {
+        Self {
+            image,
+            srgb: true,
+            filter: wgpu::FilterMode::Linear,
+            address_mode: wgpu::AddressMode::ClampToEdge,
+        }
+    }
+}
+
+/// Decodes an image file for a [`TextureBinding`], in any format the `image`
+/// crate was built with.
+pub fn load_image(path: &Path) -> Result<image::RgbaImage, String> {
+    image::open(path)
+        .map(|image| image.into_rgba8())
+        .map_err(|err| format!("Failed to load {}: {err}", path.display()))
+}
+
+/// The textures and samplers backing a controller's [`TextureBinding`]s.
+/// Images may be replaced, but their number is fixed when the pipeline is
+/// created, so the pipeline is rebuilt when it changes.
+pub struct TextureBindGroup {
+    pub layout: wgpu::BindGroupLayout,
+    count: usize,
+    bind_group: wgpu::BindGroup,
+}
+
+impl TextureBindGroup {
+    pub fn new(ctx: &GraphicsContext, textures: &[TextureBinding]) -> Self {
+        let entries = (0..textures.len() as u32)
+            .flat_map(|i| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i,
+                        visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        let layout = ctx
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Controller Texture Bind Group Layout"),
+                entries: &entries,
+            });
+        let bind_group = create_texture_bind_group(ctx, &layout, textures);
+        Self {
+            layout,
+            count: textures.len(),
+            bind_group,
+        }
+    }
+
+    /// Whether there are as many `textures` as this was created with.
+    pub fn matches(&self, textures: &[TextureBinding]) -> bool {
+        textures.len() == self.count
+    }
+
+    /// Uploads new images for the existing bindings, which `textures` must
+    /// [match](TextureBindGroup::matches).
+    pub fn update(&mut self, ctx: &GraphicsContext, textures: &[TextureBinding]) {
+        self.bind_group = create_texture_bind_group(ctx, &self.layout, textures);
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+fn create_texture_bind_group(
+    ctx: &GraphicsContext,
+    layout: &wgpu::BindGroupLayout,
+    textures: &[TextureBinding],
+) -> wgpu::BindGroup {
+    let textures = textures
+        .iter()
+        .map(|texture| Texture::from_image(&ctx.device, &ctx.queue, texture, "Controller Texture"))
+        .collect::<Vec<_>>();
+    let entries = textures
+        .iter()
+        .enumerate()
+        .flat_map(|(i, texture)| {
+            [
+                wgpu::BindGroupEntry {
+                    binding: 2 * i as u32,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2 * i as u32 + 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+    ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Controller Texture Bind Group"),
+        layout,
+        entries: &entries,
+    })
+}
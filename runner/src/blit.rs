@@ -0,0 +1,269 @@
+//! Fullscreen passes between the shader's output and the window.
+
+use crate::targets::HDR_FORMAT;
+use strum::{Display, EnumIter, EnumString};
+
+/// Curve mapping the HDR target's unbounded values into the displayable range
+#[derive(Clone, Copy, PartialEq, Debug, EnumString, EnumIter, Display)]
+pub enum Tonemapper {
+    /// Clips values above 1, like rendering to the window directly
+    None,
+    Reinhard,
+    #[strum(serialize = "ACES")]
+    Aces,
+    AgX,
+}
+
+/// Filter used when the render scale makes the shader's output smaller or
+/// larger than the window
+#[derive(Clone, Copy, PartialEq, Debug, EnumString, EnumIter, Display)]
+pub enum Upscaling {
+    Bilinear,
+    Nearest,
+}
+
+impl Upscaling {
+    pub fn filter(self) -> wgpu::FilterMode {
+        match self {
+            Self::Bilinear => wgpu::FilterMode::Linear,
+            Self::Nearest => wgpu::FilterMode::Nearest,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tonemapping {
+    pub tonemapper: Tonemapper,
+    /// Stops of exposure, each doubling the brightness
+    pub exposure: f32,
+}
+
+/// Matches `Tonemapping` in blit.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapConstants {
+    exposure: f32,
+    tonemapper: u32,
+}
+
+pub struct Blit {
+    layout: wgpu::BindGroupLayout,
+    downsample: wgpu::RenderPipeline,
+    accumulate: wgpu::RenderPipeline,
+    tonemap: wgpu::RenderPipeline,
+}
+
+impl Blit {
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        let module = device.create_shader_module(wgpu::include_wgsl!("blit.wgsl"));
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::FRAGMENT,
+                range: 0..std::mem::size_of::<TonemapConstants>() as u32,
+            }],
+        });
+        let create_pipeline = |label, entry_point, target: wgpu::ColorTargetState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: "main_vs",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point,
+                    targets: &[Some(target)],
+                }),
+                multiview: None,
+            })
+        };
+        Self {
+            downsample: create_pipeline("Downsample Pipeline", "downsample", HDR_FORMAT.into()),
+            // Mixes in the new frame with the weight of the blend constant
+            accumulate: create_pipeline(
+                "Accumulate Pipeline",
+                "copy",
+                wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Constant,
+                            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Constant,
+                            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                },
+            ),
+            tonemap: create_pipeline("Tonemap Pipeline", "tonemap", output_format.into()),
+            layout,
+        }
+    }
+
+    /// `filter` applies when [`Blit::tonemap`] scales the source
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        filter: wgpu::FilterMode,
+    ) -> wgpu::BindGroup {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        })
+    }
+
+    /// Averages each `factor`×`factor` block of the source into one pixel of
+    /// `target`, which must have [`HDR_FORMAT`].
+    pub fn downsample(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        factor: u32,
+    ) {
+        self.draw(
+            encoder,
+            "Downsample Render Pass",
+            &self.downsample,
+            source,
+            target,
+            bytemuck::bytes_of(&factor),
+            None,
+        );
+    }
+
+    /// Blends the source into `target`, a running average over
+    /// `frames` frames, so that it becomes the average over `frames + 1`.
+    pub fn accumulate(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        frames: u32,
+    ) {
+        self.draw(
+            encoder,
+            "Accumulate Render Pass",
+            &self.accumulate,
+            source,
+            target,
+            &[],
+            Some(1.0 / (frames + 1) as f64),
+        );
+    }
+
+    /// Maps the HDR source into the output format, stretching it over `target`.
+    pub fn tonemap(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        tonemapping: Tonemapping,
+    ) {
+        let constants = TonemapConstants {
+            exposure: tonemapping.exposure.exp2(),
+            tonemapper: tonemapping.tonemapper as u32,
+        };
+        self.draw(
+            encoder,
+            "Tonemap Render Pass",
+            &self.tonemap,
+            source,
+            target,
+            bytemuck::bytes_of(&constants),
+            None,
+        );
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        pipeline: &wgpu::RenderPipeline,
+        source: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        push_constants: &[u8],
+        // Blending keeps what the target holds
+        blend_constant: Option<f64>,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: match blend_constant {
+                        Some(_) => wgpu::LoadOp::Load,
+                        None => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    },
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, source, &[]);
+        if !push_constants.is_empty() {
+            rpass.set_push_constants(wgpu::ShaderStages::FRAGMENT, 0, push_constants);
+        }
+        if let Some(weight) = blend_constant {
+            rpass.set_blend_constant(wgpu::Color {
+                r: weight,
+                g: weight,
+                b: weight,
+                a: weight,
+            });
+        }
+        rpass.draw(0..3, 0..1);
+    }
+}
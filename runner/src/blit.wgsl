@@ -0,0 +1,111 @@
+// Fullscreen passes that read one texture and write another, see blit.rs
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+// Source texels per target pixel along each axis
+var<push_constant> factor: u32;
+
+struct Tonemapping {
+    // Multiplier applied before the curve
+    exposure: f32,
+    // Index of the `Tonemapper` variant
+    tonemapper: u32,
+}
+
+var<push_constant> tonemapping: Tonemapping;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position in the source, from (0, 0) at the top left to (1, 1)
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn main_vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // A triangle covering the whole target
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn downsample(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let origin = vec2<u32>(position.xy) * factor;
+    var sum = vec4<f32>(0.0);
+    for (var y = 0u; y < factor; y += 1u) {
+        for (var x = 0u; x < factor; x += 1u) {
+            sum += textureLoad(source, origin + vec2<u32>(x, y), 0);
+        }
+    }
+    return sum / f32(factor * factor);
+}
+
+// Blended into the accumulated average, see `Blit::accumulate`
+@fragment
+fn copy(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<u32>(position.xy), 0);
+}
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = color * (2.51 * color + 0.03);
+    let b = color * (2.43 * color + 0.59) + 0.14;
+    return clamp(a / b, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Polynomial fit of the AgX base contrast curve, from Benjamin Wrensch's
+// minimal AgX
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2
+        + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    var x = inset * max(color, vec3<f32>(1e-10));
+    x = clamp(log2(x), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    x = agx_contrast((x - min_ev) / (max_ev - min_ev));
+    // The curve produces display-encoded values, and the target re-encodes
+    return pow(max(outset * x, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+// Also scales the source to the target's size, with the sampler's filter
+@fragment
+fn tonemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(source, source_sampler, in.uv);
+    let color = texel.rgb * tonemapping.exposure;
+    switch tonemapping.tonemapper {
+        case 1u: {
+            return vec4<f32>(reinhard(color), texel.a);
+        }
+        case 2u: {
+            return vec4<f32>(aces(color), texel.a);
+        }
+        case 3u: {
+            return vec4<f32>(agx(color), texel.a);
+        }
+        default: {
+            return vec4<f32>(color, texel.a);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use web_time::Instant;
+
+/// Length of a single step while paused, in seconds of animation time
+pub const STEP: f32 = 1.0 / 60.0;
+
+/// Animation time passed to the controllers. Follows the wall clock scaled by
+/// `speed`, but can be paused, stepped and moved to an arbitrary time.
+pub struct Clock {
+    time: f32,
+    last_tick: Instant,
+    paused: bool,
+    speed: f32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            last_tick: Instant::now(),
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Advances the clock by the wall time since the previous tick and
+    /// returns the current time.
+    pub fn tick(&mut self) -> f32 {
+        self.tick_at(Instant::now())
+    }
+
+    fn tick_at(&mut self, now: Instant) -> f32 {
+        if !self.paused {
+            self.time += (now - self.last_tick).as_secs_f32() * self.speed;
+        }
+        self.last_tick = now;
+        self.time
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0);
+    }
+
+    /// Moves the clock by `frames` steps, backwards if negative.
+    pub fn step(&mut self, frames: i32) {
+        self.paused = true;
+        self.seek(self.time + frames as f32 * STEP);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    /// A clock whose ticks happen at `start + seconds`
+    fn clock() -> (Clock, impl Fn(f32) -> Instant) {
+        let clock = Clock::new();
+        let start = clock.last_tick;
+        (clock, move |seconds| {
+            start + Duration::from_secs_f32(seconds)
+        })
+    }
+
+    #[test]
+    fn test_tick() {
+        let (mut clock, at) = clock();
+        assert_eq!(clock.tick_at(at(0.5)), 0.5);
+        assert_eq!(clock.tick_at(at(1.5)), 1.5);
+    }
+
+    #[test]
+    fn test_pause() {
+        let (mut clock, at) = clock();
+        clock.tick_at(at(1.0));
+        clock.set_paused(true);
+        assert_eq!(clock.tick_at(at(3.0)), 1.0);
+        // Time spent paused isn't caught up on resume
+        clock.set_paused(false);
+        assert_eq!(clock.tick_at(at(3.5)), 1.5);
+    }
+
+    #[test]
+    fn test_step() {
+        let (mut clock, at) = clock();
+        clock.tick_at(at(1.0));
+        clock.step(3);
+        assert!(clock.paused());
+        assert_eq!(clock.tick_at(at(2.0)), 1.0 + 3.0 * STEP);
+        clock.step(-2);
+        assert_eq!(clock.tick_at(at(3.0)), 1.0 + STEP);
+        // Stepping back past the start stops at zero
+        clock.step(-1000);
+        assert_eq!(clock.tick_at(at(4.0)), 0.0);
+    }
+
+    #[test]
+    fn test_speed() {
+        let (mut clock, at) = clock();
+        clock.set_speed(2.0);
+        assert_eq!(clock.tick_at(at(1.0)), 2.0);
+        clock.set_speed(-0.5);
+        assert_eq!(clock.tick_at(at(2.0)), 1.5);
+    }
+
+    #[test]
+    fn test_seek() {
+        let (mut clock, at) = clock();
+        clock.seek(10.0);
+        assert_eq!(clock.tick_at(at(1.0)), 11.0);
+        clock.seek(-1.0);
+        assert_eq!(clock.tick_at(at(1.0)), 0.0);
+        assert!(!clock.paused());
+    }
+}
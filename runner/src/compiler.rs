@@ -0,0 +1,233 @@
+//! Compiles the shader crates on a background thread so the event loop never
+//! waits for rust-gpu, and recompiles the selected shader when its sources change.
+//! The sources are the shader's crate and every file its last build read, see
+//! [`crate::dep_info`].
+
+use crate::{
+    diagnostics,
+    shader::{self, crate_path, shaders_path},
+    window::UserEvent,
+    Options, RustGPUShader,
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+};
+use strum::IntoEnumIterator;
+use winit::event_loop::EventLoopProxy;
+
+pub struct ShaderCompiler {
+    queue: Arc<Queue>,
+    watcher: Arc<Mutex<SourceWatcher>>,
+}
+
+impl ShaderCompiler {
+    /// Starts compiling every shader, beginning with `options.shader`.
+    /// Results are delivered as [`UserEvent::NewModule`] or [`UserEvent::CompileFailed`],
+    /// and problems watching the sources as [`UserEvent::WatchFailed`].
+    pub fn new(options: &Options, event_proxy: EventLoopProxy<UserEvent>) -> Self {
+        let queue = Arc::new(Queue::default());
+        queue.push_back(options.shader);
+        for shader in RustGPUShader::iter().filter(|&shader| shader != options.shader) {
+            queue.push_back(shader);
+        }
+
+        let watcher = Arc::new(Mutex::new(SourceWatcher::new(
+            options.shader,
+            queue.clone(),
+            event_proxy.clone(),
+        )));
+
+        {
+            let queue = queue.clone();
+            let watcher = watcher.clone();
+            let options = options.clone();
+            std::thread::spawn(move || loop {
+                let shader = queue.pop();
+                let _ = event_proxy.send_event(UserEvent::CompileStarted(shader));
+                let event = match shader::compile(&options, shader) {
+                    Ok((modules, sources)) => {
+                        if let Some(sources) = sources {
+                            watcher.lock().unwrap().set_sources(shader, sources);
+                        }
+                        UserEvent::NewModule(shader, modules)
+                    }
+                    Err(err) => {
+                        UserEvent::CompileFailed(shader, diagnostics::collect(shader, &err))
+                    }
+                };
+                if event_proxy.send_event(event).is_err() {
+                    // The event loop has exited
+                    break;
+                }
+            });
+        }
+
+        Self { queue, watcher }
+    }
+
+    /// Moves the file watcher to `shader`, and compiles it next if it is
+    /// still waiting in the queue.
+    pub fn select(&mut self, shader: RustGPUShader) {
+        self.watcher.lock().unwrap().select(shader);
+        self.queue.promote(shader);
+    }
+}
+
+/// Queues the selected shader whenever one of its sources changes.
+struct SourceWatcher {
+    /// `None` if it couldn't be created, which has been reported
+    watcher: Option<RecommendedWatcher>,
+    /// Shared with the watcher's callback, which must not lock the
+    /// `SourceWatcher` as that may be waiting on the callback's thread
+    selected: Arc<Mutex<RustGPUShader>>,
+    /// Files read by the latest build of each shader, indexed by `RustGPUShader`
+    sources: Vec<Vec<PathBuf>>,
+    watched: Vec<(PathBuf, RecursiveMode)>,
+    event_proxy: EventLoopProxy<UserEvent>,
+}
+
+impl SourceWatcher {
+    fn new(
+        shader: RustGPUShader,
+        queue: Arc<Queue>,
+        event_proxy: EventLoopProxy<UserEvent>,
+    ) -> Self {
+        let selected = Arc::new(Mutex::new(shader));
+        let watcher = {
+            let selected = selected.clone();
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.map_or(false, |event| !event.kind.is_access()) {
+                    queue.push_front(*selected.lock().unwrap());
+                }
+            })
+        };
+        let watcher = watcher
+            .map_err(|err| {
+                let _ = event_proxy.send_event(UserEvent::WatchFailed(format!(
+                    "Failed to create file watcher: {err}"
+                )));
+            })
+            .ok();
+        let mut source_watcher = Self {
+            watcher,
+            selected,
+            sources: RustGPUShader::iter().map(|_| vec![]).collect(),
+            watched: vec![],
+            event_proxy,
+        };
+        source_watcher.rewatch();
+        source_watcher
+    }
+
+    fn select(&mut self, shader: RustGPUShader) {
+        let previous = std::mem::replace(&mut *self.selected.lock().unwrap(), shader);
+        if previous != shader {
+            self.rewatch();
+        }
+    }
+
+    fn set_sources(&mut self, shader: RustGPUShader, sources: Vec<PathBuf>) {
+        if self.sources[shader as usize] != sources {
+            self.sources[shader as usize] = sources;
+            if *self.selected.lock().unwrap() == shader {
+                self.rewatch();
+            }
+        }
+    }
+
+    /// Paths to watch for the selected shader: its crate, and the directories
+    /// of the files its latest build read. Until a build reports those, the
+    /// `shared` crate stands in for them.
+    fn paths(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let shader = *self.selected.lock().unwrap();
+        let crate_path = canonicalize(crate_path(shader));
+        let sources = &self.sources[shader as usize];
+        let mut paths = vec![(crate_path.clone(), RecursiveMode::Recursive)];
+        if sources.is_empty() {
+            paths.push((
+                canonicalize(shaders_path().join("shared")),
+                RecursiveMode::Recursive,
+            ));
+        }
+        for dir in sources.iter().filter_map(|source| source.parent()) {
+            if !dir.starts_with(&crate_path) && !paths.iter().any(|(path, _)| path == dir) {
+                paths.push((dir.to_owned(), RecursiveMode::NonRecursive));
+            }
+        }
+        paths
+    }
+
+    fn rewatch(&mut self) {
+        let paths = self.paths();
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        for (path, _) in &self.watched {
+            if !paths.iter().any(|(p, _)| p == path) {
+                let _ = watcher.unwatch(path);
+            }
+        }
+        let mut errors = vec![];
+        for (path, mode) in &paths {
+            if !self.watched.contains(&(path.clone(), *mode)) {
+                if let Err(err) = watcher.watch(path, *mode) {
+                    errors.push(format!("Failed to watch {}: {err}", path.display()));
+                }
+            }
+        }
+        self.watched = paths;
+        if !errors.is_empty() {
+            let _ = self
+                .event_proxy
+                .send_event(UserEvent::WatchFailed(errors.join("\n")));
+        }
+    }
+}
+
+fn canonicalize(path: PathBuf) -> PathBuf {
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+/// Shaders waiting to be compiled, without duplicates.
+#[derive(Default)]
+struct Queue {
+    pending: Mutex<VecDeque<RustGPUShader>>,
+    ready: Condvar,
+}
+
+impl Queue {
+    fn push_back(&self, shader: RustGPUShader) {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.contains(&shader) {
+            pending.push_back(shader);
+        }
+        self.ready.notify_one();
+    }
+
+    fn push_front(&self, shader: RustGPUShader) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|&s| s != shader);
+        pending.push_front(shader);
+        self.ready.notify_one();
+    }
+
+    fn promote(&self, shader: RustGPUShader) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(i) = pending.iter().position(|&s| s == shader) {
+            pending.remove(i);
+            pending.push_front(shader);
+        }
+    }
+
+    fn pop(&self) -> RustGPUShader {
+        let pending = self.pending.lock().unwrap();
+        let mut pending = self
+            .ready
+            .wait_while(pending, |pending| pending.is_empty())
+            .unwrap();
+        pending.pop_front().unwrap()
+    }
+}
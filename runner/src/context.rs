@@ -1,90 +1,337 @@
-use crate::Options;
+use crate::{targets::HDR_FORMAT, texture::Texture, Options};
+use std::sync::{Arc, Mutex};
 use winit::window::Window;
 
 pub struct GraphicsContext {
-    pub surface: wgpu::Surface,
+    /// `None` when rendering offscreen, see [`GraphicsContext::new_headless`].
+    pub surface: Option<wgpu::Surface>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
+    /// MSAA sample counts supported by both the HDR and depth formats
+    pub sample_counts: Vec<u32>,
+    /// The automatic modes followed by those the surface supports, empty
+    /// without a surface
+    pub present_modes: Vec<wgpu::PresentMode>,
+    pub adapter_info: wgpu::AdapterInfo,
+    /// Formats the surface supports, or the offscreen target's
+    pub surface_formats: Vec<wgpu::TextureFormat>,
+    /// Why the device has to be recreated, set when it is lost or runs out
+    /// of memory
+    failure: Arc<Mutex<Option<String>>>,
 }
 
 impl GraphicsContext {
-    pub async fn new(window: &Window, options: &Options) -> GraphicsContext {
-        let backends = wgpu::util::backend_bits_from_env()
-            .unwrap_or(wgpu::Backends::VULKAN | wgpu::Backends::METAL);
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends,
-            dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
-        });
+    pub async fn new(window: &Window, options: &Options) -> Result<GraphicsContext, String> {
+        let instance = create_instance(options);
 
         let initial_surface = unsafe { instance.create_surface(&window) }
-            .expect("Failed to create surface from window");
+            .map_err(|err| format!("Failed to create surface from window: {err}"))?;
 
-        let adapter = wgpu::util::initialize_adapter_from_env_or_default(
-            &instance,
-            // Request an adapter which can render to our surface
-            Some(&initial_surface),
-        )
-        .await
-        .expect("Failed to find an appropriate adapter");
+        // Request an adapter which can render to our surface
+        let adapter = request_adapter(&instance, options, Some(&initial_surface)).await?;
 
-        let mut features = wgpu::Features::PUSH_CONSTANTS;
-        if options.force_spirv_passthru {
-            features |= wgpu::Features::SPIRV_SHADER_PASSTHROUGH;
-        }
-        let limits = wgpu::Limits {
-            max_push_constant_size: 128,
-            ..Default::default()
-        };
+        let (device, queue) = request_device(&adapter, options).await?;
+        let failure = handle_device_failures(&device);
 
-        // Create the logical device and command queue
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features,
-                    limits,
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
+        let capabilities = initial_surface.get_capabilities(&adapter);
+        let mut present_modes = vec![wgpu::PresentMode::AutoVsync, wgpu::PresentMode::AutoNoVsync];
+        present_modes.extend(&capabilities.present_modes);
+        let present_mode = if present_modes.contains(&options.present_mode) {
+            options.present_mode
+        } else {
+            eprintln!(
+                "{:?} is not supported by the surface, using AutoVsync",
+                options.present_mode
+            );
+            wgpu::PresentMode::AutoVsync
+        };
 
         let auto_configure_surface =
             |adapter: &_, device: &_, surface: wgpu::Surface, size: winit::dpi::PhysicalSize<_>| {
                 let mut surface_config = surface
                     .get_default_config(adapter, size.width, size.height)
-                    .unwrap_or_else(|| {
-                        panic!(
+                    .ok_or_else(|| {
+                        format!(
                             "Missing formats/present modes in surface capabilities: {:#?}",
                             surface.get_capabilities(adapter)
                         )
-                    });
+                    })?;
 
-                // TODO: make configurable
-                surface_config.present_mode = wgpu::PresentMode::AutoVsync;
+                surface_config.present_mode = present_mode;
 
                 surface.configure(device, &surface_config);
 
-                (surface, surface_config)
+                Ok::<_, String>((surface, surface_config))
             };
         let (surface, config) =
-            auto_configure_surface(&adapter, &device, initial_surface, window.inner_size());
+            auto_configure_surface(&adapter, &device, initial_surface, window.inner_size())?;
+        let sample_counts = sample_counts(&adapter, &device);
+
+        Ok(GraphicsContext {
+            surface: Some(surface),
+            device,
+            queue,
+            config,
+            sample_counts,
+            present_modes,
+            adapter_info: adapter.get_info(),
+            surface_formats: capabilities.formats,
+            failure,
+        })
+    }
+
+    /// Creates a context without a window. `config` describes the offscreen
+    /// target that frames are rendered into instead of a swapchain.
+    pub async fn new_headless(options: &Options) -> GraphicsContext {
+        let instance = create_instance(options);
+
+        let adapter = request_adapter(&instance, options, None)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        let (device, queue) = request_device(&adapter, options)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+        let failure = handle_device_failures(&device);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: options.size.width,
+            height: options.size.height,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+        let sample_counts = sample_counts(&adapter, &device);
 
         GraphicsContext {
-            surface,
+            surface: None,
+            surface_formats: vec![config.format],
             device,
             queue,
             config,
+            sample_counts,
+            present_modes: vec![],
+            adapter_info: adapter.get_info(),
+            failure,
         }
     }
 
-    pub fn set_vsync(&mut self, enable: bool) {
-        self.config.present_mode = if enable {
-            wgpu::PresentMode::AutoVsync
+    /// Why the device has to be recreated, if it does
+    pub fn failure(&self) -> Option<String> {
+        self.failure.lock().unwrap().clone()
+    }
+
+    /// Marks the device as unusable, for failures that wgpu reports through
+    /// a `SurfaceError`.
+    pub fn fail(&self, reason: String) {
+        self.failure.lock().unwrap().get_or_insert(reason);
+    }
+
+    pub fn configure_surface(&self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// The largest supported MSAA sample count up to `requested`
+    pub fn supported_sample_count(&self, requested: u32) -> u32 {
+        self.sample_counts
+            .iter()
+            .copied()
+            .filter(|&count| count <= requested)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// `mode` must be one of `present_modes`
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        self.config.present_mode = mode;
+        self.configure_surface();
+    }
+
+    /// The adapter, surface and device limits, for bug reports
+    pub fn adapter_report(&self) -> String {
+        let wgpu::AdapterInfo {
+            name,
+            vendor,
+            device,
+            device_type,
+            driver,
+            driver_info,
+            backend,
+        } = &self.adapter_info;
+        format!(
+            "Adapter: {name} ({device_type:?})\n\
+             Vendor: {vendor:#06x}, device: {device:#06x}\n\
+             Backend: {backend:?}\n\
+             Driver: {driver} {driver_info}\n\
+             Present mode: {:?}\n\
+             Surface formats: {:?}\n\
+             Features: {:?}\n\
+             Limits: {:#?}",
+            self.config.present_mode,
+            self.surface_formats,
+            self.device.features(),
+            self.device.limits(),
+        )
+    }
+}
+
+fn sample_counts(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Vec<u32> {
+    let flags = |format: wgpu::TextureFormat| {
+        if device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        {
+            adapter.get_texture_format_features(format).flags
         } else {
-            wgpu::PresentMode::AutoNoVsync
-        };
-        self.surface.configure(&self.device, &self.config);
+            format.guaranteed_format_features(device.features()).flags
+        }
+    };
+    let color = flags(HDR_FORMAT);
+    let depth = flags(Texture::DEPTH_FORMAT);
+    [1, 2, 4, 8]
+        .into_iter()
+        .filter(|&count| {
+            count == 1
+                || color.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                    && color.sample_count_supported(count)
+                    && depth.sample_count_supported(count)
+        })
+        .collect()
+}
+
+/// Prints the adapters of the selected backends, for `--list-adapters`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_adapters(options: &Options) {
+    let instance = create_instance(options);
+    let adapters = instance.enumerate_adapters(backends(options));
+    if adapters.len() == 0 {
+        println!("No adapters found");
     }
+    for adapter in adapters {
+        let wgpu::AdapterInfo {
+            name,
+            device_type,
+            driver,
+            driver_info,
+            backend,
+            ..
+        } = adapter.get_info();
+        println!("{name} ({backend:?}, {device_type:?}) {driver} {driver_info}");
+    }
+}
+
+/// `--backend`, then `WGPU_BACKEND`, then every backend when looking for
+/// an adapter by name, or Vulkan and Metal
+fn backends(options: &Options) -> wgpu::Backends {
+    options
+        .backend
+        .or_else(wgpu::util::backend_bits_from_env)
+        .unwrap_or(if options.adapter.is_some() || options.list_adapters {
+            wgpu::Backends::all()
+        } else {
+            wgpu::Backends::VULKAN | wgpu::Backends::METAL
+        })
+}
+
+fn create_instance(options: &Options) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backends(options),
+        dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
+    })
+}
+
+/// Records running out of memory in the returned failure, instead of
+/// panicking like wgpu's default error handler. Other errors are bugs and
+/// still panic.
+fn handle_device_failures(device: &wgpu::Device) -> Arc<Mutex<Option<String>>> {
+    let failure = Arc::<Mutex<Option<String>>>::default();
+    let handler_failure = failure.clone();
+    device.on_uncaptured_error(Box::new(move |error| {
+        if let wgpu::Error::OutOfMemory { .. } = error {
+            handler_failure
+                .lock()
+                .unwrap()
+                .get_or_insert(error.to_string());
+        } else {
+            panic!("wgpu error: {error}");
+        }
+    }));
+    failure
+}
+
+/// The first adapter whose name contains `--adapter` or else
+/// `WGPU_ADAPTER_NAME`, ignoring case, or the one the power preference
+/// favours, from `--power-preference` or `WGPU_POWER_PREF`.
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    options: &Options,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> Result<wgpu::Adapter, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(name) = &options.adapter {
+        let lowercase_name = name.to_lowercase();
+        return instance
+            .enumerate_adapters(backends(options))
+            .find(|adapter| {
+                adapter
+                    .get_info()
+                    .name
+                    .to_lowercase()
+                    .contains(&lowercase_name)
+                    && compatible_surface
+                        .map_or(true, |surface| adapter.is_surface_supported(surface))
+            })
+            .ok_or_else(|| format!("No adapter matches {name:?}, see --list-adapters"));
+    }
+    if let Some(adapter) = wgpu::util::initialize_adapter_from_env(instance, compatible_surface) {
+        return Ok(adapter);
+    }
+    let power_preference = options
+        .power_preference
+        .or_else(wgpu::util::power_preference_from_env)
+        .unwrap_or_default();
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            force_fallback_adapter: false,
+            compatible_surface,
+        })
+        .await
+        .ok_or_else(|| "Failed to find an appropriate adapter".to_string())
+}
+
+async fn request_device(
+    adapter: &wgpu::Adapter,
+    options: &Options,
+) -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let mut features = wgpu::Features::PUSH_CONSTANTS;
+    if options.force_spirv_passthru {
+        features |= wgpu::Features::SPIRV_SHADER_PASSTHROUGH;
+    }
+    // Without this only 1x and 4x MSAA are reported, whatever the adapter supports
+    features |= adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+    // Frame timings fall back to the CPU without it, see `crate::profiler`
+    features |= adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+    let limits = wgpu::Limits {
+        max_push_constant_size: 128,
+        ..Default::default()
+    };
+
+    // Create the logical device and command queue
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features,
+                limits,
+            },
+            None,
+        )
+        .await
+        .map_err(|err| format!("Failed to create device: {err}"))
 }
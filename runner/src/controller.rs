@@ -1,13 +1,23 @@
+use crate::bindings::{Binding, TextureBinding};
 use crate::model::Vertex;
 use crate::window::UserEvent;
 use crate::{shaders, RustGPUShader};
 use egui::{Context, Ui};
+use glam::Vec2;
 use shaders::*;
 use winit::dpi::PhysicalSize;
-use winit::event::{ElementState, MouseScrollDelta};
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseScrollDelta};
 use winit::event_loop::EventLoopProxy;
 use winit::{dpi::PhysicalPosition, event::MouseButton};
 
+pub use ui_derive::ParamsUi;
+
+/// Implemented by `#[derive(ParamsUi)]`, see [`ui_derive`]
+pub trait ParamsUi {
+    /// Draws the `#[param]` fields, returning whether any of them changed
+    fn params_ui(&mut self, ui: &mut Ui) -> bool;
+}
+
 pub trait Controller {
     fn new(size: PhysicalSize<u32>) -> Self
     where
@@ -15,16 +25,75 @@ pub trait Controller {
     fn mouse_input(&mut self, _state: ElementState, _button: MouseButton) {}
     fn mouse_move(&mut self, _position: PhysicalPosition<f64>) {}
     fn mouse_scroll(&mut self, _delta: MouseScrollDelta) {}
+    fn keyboard_input(&mut self, _input: KeyboardInput) {}
+    fn modifiers_changed(&mut self, _modifiers: ModifiersState) {}
+    /// The window lost focus or another shader became active, so keys and
+    /// buttons held now won't report their release
+    fn focus_lost(&mut self) {}
+    /// Pinch gesture, `scale` is greater than 1 when zooming in
+    fn pinch(&mut self, _scale: f32) {}
+    /// Two-finger drag by `delta` pixels
+    fn pan(&mut self, _delta: Vec2) {}
+    /// Rotation gesture in radians, counterclockwise
+    fn rotate(&mut self, _angle: f32) {}
     fn resize(&mut self, size: PhysicalSize<u32>);
-    fn update(&mut self);
+    fn update(&mut self, time: f32);
     fn push_constants(&self) -> &[u8];
-    fn ui(&mut self, _ctx: &Context, _ui: &mut Ui, _event_proxy: &EventLoopProxy<UserEvent>) {}
-    fn has_ui(&self) -> bool {
-        false
+    /// Offsets the fragment coordinates of the following frames by `jitter`
+    /// pixels, each component in [-0.5, 0.5), so that accumulated frames
+    /// sample the whole pixel. Shaders that ignore it accumulate without
+    /// anti-aliasing.
+    fn set_jitter(&mut self, _jitter: Vec2) {}
+    /// Compared between frames to restart accumulation when the image
+    /// changes. Defaults to the push constants, so animated shaders restart
+    /// as time passes; shaders that don't read `time` leave it out.
+    fn accumulation_key(&self) -> Vec<u8> {
+        self.push_constants().to_vec()
+    }
+    /// The tunable state drawn by the default `ui`
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        None
+    }
+    fn ui(&mut self, _ctx: &Context, ui: &mut Ui, _event_proxy: &EventLoopProxy<UserEvent>) {
+        if let Some(params) = self.params() {
+            params.params_ui(ui);
+        }
+    }
+    fn has_ui(&mut self) -> bool {
+        self.params().is_some()
     }
     fn buffers(&self) -> Option<(&[Vertex],&[u32])> {
         None
     }
+    /// Uniform and storage buffers, uploaded every frame except for writable
+    /// storage buffers, see [`crate::bindings`]
+    fn bindings(&self) -> Vec<Binding<'_>> {
+        vec![]
+    }
+    /// Workgroup counts for the shader's `main_cs` entry point, which runs
+    /// before each frame is drawn. Shaders without a compute pass return `None`.
+    fn dispatch(&self) -> Option<[u32; 3]> {
+        None
+    }
+    /// Called once `main_cs` has been dispatched with the current push
+    /// constants, e.g. to swap ping-pong buffers
+    fn dispatched(&mut self) {}
+    /// Number of offscreen passes before `main_fs`, see [`crate::feedback`]
+    fn feedback_passes(&self) -> usize {
+        0
+    }
+    /// Images sampled by the shaders, uploaded when the pipeline is
+    /// created and after [`UserEvent::NewTexturesReady`]
+    fn textures(&self) -> Vec<TextureBinding<'_>> {
+        vec![]
+    }
+    /// Serializes the user-tunable state as RON, see [`crate::presets`].
+    fn preset(&self) -> Option<String> {
+        None
+    }
+    fn load_preset(&mut self, _preset: &str) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 pub fn new_controller(shader: RustGPUShader, size: PhysicalSize<u32>) -> Box<dyn Controller> {
@@ -44,5 +113,9 @@ pub fn new_controller(shader: RustGPUShader, size: PhysicalSize<u32>) -> Box<dyn
         RustGPUShader::SphericalHarmonicsShape => {
             Box::new(spherical_harmonics_shape::Controller::new(size))
         }
+        RustGPUShader::Voronoi => Box::new(voronoi::Controller::new(size)),
+        RustGPUShader::Lens => Box::new(lens::Controller::new(size)),
+        RustGPUShader::GameOfLife => Box::new(game_of_life::Controller::new(size)),
+        RustGPUShader::Trails => Box::new(trails::Controller::new(size)),
     }
 }
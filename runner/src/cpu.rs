@@ -0,0 +1,115 @@
+//! Reference renderer that evaluates the shader crates' `main_fs` on the host.
+
+use crate::RustGPUShader;
+use bytemuck::Pod;
+use glam::{vec4, Vec4};
+use rayon::prelude::*;
+use winit::dpi::PhysicalSize;
+
+#[cfg(test)]
+mod test;
+
+const TILE_SIZE: u32 = 32;
+
+type FragmentShader<C> = fn(Vec4, &C, &mut Vec4);
+
+/// Renders one frame of `shader` with the given push constants. Returns `None`
+/// for shaders that are not a single fullscreen fragment pass.
+pub fn render(
+    shader: RustGPUShader,
+    push_constants: &[u8],
+    size: PhysicalSize<u32>,
+) -> Option<image::RgbaImage> {
+    Some(match shader {
+        RustGPUShader::Mandelbrot => render_fs(::mandelbrot::main_fs, push_constants, size),
+        RustGPUShader::RayMarching => render_fs(::ray_marching::main_fs, push_constants, size),
+        RustGPUShader::RayMarching2D => render_fs(::ray_marching_2d::main_fs, push_constants, size),
+        RustGPUShader::SierpinskiTriangle => {
+            render_fs(::sierpinski_triangle::main_fs, push_constants, size)
+        }
+        RustGPUShader::KochSnowflake => render_fs(::koch_snowflake::main_fs, push_constants, size),
+        RustGPUShader::SDFs2D => render_fs(::sdfs_2d::main_fs, push_constants, size),
+        RustGPUShader::SDFs3D => render_fs(::sdfs_3d::main_fs, push_constants, size),
+        RustGPUShader::HydrogenWavefunction => {
+            render_fs(::hydrogen_wavefunction::main_fs, push_constants, size)
+        }
+        RustGPUShader::SphericalHarmonics => {
+            render_fs(::spherical_harmonics::main_fs, push_constants, size)
+        }
+        // Depends on `geometric_algebra`, which lives outside the repository
+        RustGPUShader::Gaussian => return None,
+        // Draws a mesh rather than a fullscreen triangle
+        RustGPUShader::SphericalHarmonicsShape => return None,
+        // Read buffers or textures, which only the GPU pipeline binds
+        RustGPUShader::Voronoi | RustGPUShader::Lens => return None,
+        // Run a compute pass or feedback passes first
+        RustGPUShader::GameOfLife | RustGPUShader::Trails => return None,
+    })
+}
+
+fn render_fs<C: Pod + Sync>(
+    main_fs: FragmentShader<C>,
+    push_constants: &[u8],
+    size: PhysicalSize<u32>,
+) -> image::RgbaImage {
+    let constants: C = bytemuck::pod_read_unaligned(push_constants);
+
+    let tiles = (0..size.height)
+        .step_by(TILE_SIZE as usize)
+        .flat_map(|y| {
+            (0..size.width)
+                .step_by(TILE_SIZE as usize)
+                .map(move |x| (x, y))
+        })
+        .collect::<Vec<_>>();
+
+    let rendered_tiles = tiles
+        .into_par_iter()
+        .map(|(x0, y0)| {
+            let x1 = (x0 + TILE_SIZE).min(size.width);
+            let y1 = (y0 + TILE_SIZE).min(size.height);
+            let pixels = (y0..y1)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    // Fragments are shaded at pixel centers
+                    let frag_coord = vec4(x as f32 + 0.5, y as f32 + 0.5, 0.0, 1.0);
+                    let mut output = Vec4::ZERO;
+                    main_fs(frag_coord, &constants, &mut output);
+                    to_rgba8(output)
+                })
+                .collect::<Vec<_>>();
+            (x0, y0, x1, pixels)
+        })
+        .collect::<Vec<_>>();
+
+    let mut image = image::RgbaImage::new(size.width, size.height);
+    for (x0, y0, x1, pixels) in rendered_tiles {
+        let tile_width = (x1 - x0) as usize;
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let x = x0 + (i % tile_width) as u32;
+            let y = y0 + (i / tile_width) as u32;
+            image.put_pixel(x, y, image::Rgba(pixel));
+        }
+    }
+    image
+}
+
+/// Matches what the GPU writes to an `*Srgb` render target.
+fn to_rgba8(color: Vec4) -> [u8; 4] {
+    let color = color.clamp(Vec4::ZERO, Vec4::ONE);
+    let encode = |x: f32| (x * 255.0).round() as u8;
+    [
+        encode(linear_to_srgb(color.x)),
+        encode(linear_to_srgb(color.y)),
+        encode(linear_to_srgb(color.z)),
+        encode(color.w),
+    ]
+}
+
+fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
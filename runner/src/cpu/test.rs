@@ -0,0 +1,170 @@
+//! Golden-image regression tests. Every shader that [`render`] supports is
+//! rendered with each [`Preset`] and compared against `runner/golden/*.png`.
+//!
+//! A missing reference fails the test. Set `UPDATE_GOLDEN=1` to write the
+//! references of new shaders or presets, or regenerate all of them after an
+//! intentional change.
+
+use super::render;
+use crate::{controller::new_controller, RustGPUShader};
+use std::path::PathBuf;
+use strum::IntoEnumIterator;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::MouseScrollDelta,
+};
+
+/// Largest per-channel difference that still counts as a matching pixel.
+const TOLERANCE: u8 = 2;
+/// Fraction of pixels allowed to exceed [`TOLERANCE`], to absorb differences
+/// in floating point math between platforms.
+const MAX_MISMATCH_RATIO: f32 = 0.001;
+
+struct Preset {
+    name: &'static str,
+    size: PhysicalSize<u32>,
+    time: f32,
+    cursor: PhysicalPosition<f64>,
+    /// Lines of mouse wheel scrolling applied before rendering.
+    zoom: f32,
+}
+
+const PRESETS: &[Preset] = &[
+    Preset {
+        name: "default",
+        size: PhysicalSize::new(160, 90),
+        time: 0.0,
+        cursor: PhysicalPosition::new(80.0, 45.0),
+        zoom: 0.0,
+    },
+    Preset {
+        name: "zoomed",
+        size: PhysicalSize::new(160, 90),
+        time: 1.5,
+        cursor: PhysicalPosition::new(40.0, 30.0),
+        zoom: 5.0,
+    },
+    Preset {
+        name: "portrait",
+        size: PhysicalSize::new(72, 128),
+        time: 4.0,
+        cursor: PhysicalPosition::new(60.0, 20.0),
+        zoom: -3.0,
+    },
+];
+
+fn render_preset(shader: RustGPUShader, preset: &Preset) -> Option<image::RgbaImage> {
+    let mut controller = new_controller(shader, preset.size);
+    controller.mouse_move(preset.cursor);
+    controller.mouse_scroll(MouseScrollDelta::LineDelta(0.0, preset.zoom));
+    controller.update(preset.time);
+    render(shader, controller.push_constants(), preset.size)
+}
+
+fn golden_dir() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "golden"].iter().collect()
+}
+
+fn failure_dir() -> PathBuf {
+    [
+        env!("CARGO_MANIFEST_DIR"),
+        "..",
+        "target",
+        "golden-failures",
+    ]
+    .iter()
+    .collect()
+}
+
+/// Returns a description of the mismatch and an image highlighting it.
+fn compare(
+    expected: &image::RgbaImage,
+    actual: &image::RgbaImage,
+) -> Result<(), (String, image::RgbaImage)> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err((
+            format!(
+                "size mismatch: expected {:?}, got {:?}",
+                expected.dimensions(),
+                actual.dimensions()
+            ),
+            actual.clone(),
+        ));
+    }
+
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+    let diff = image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let e = expected.get_pixel(x, y).0;
+        let a = actual.get_pixel(x, y).0;
+        let difference = e.iter().zip(a).map(|(e, a)| e.abs_diff(a)).max().unwrap();
+        max_difference = max_difference.max(difference);
+        if difference > TOLERANCE {
+            mismatched += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            // Dimmed reference so the mismatches stand out
+            let luma = (e[0] as u32 + e[1] as u32 + e[2] as u32) / 12;
+            image::Rgba([luma as u8, luma as u8, luma as u8, 255])
+        }
+    });
+
+    let ratio = mismatched as f32 / (actual.width() * actual.height()) as f32;
+    if ratio > MAX_MISMATCH_RATIO {
+        Err((
+            format!(
+                "{mismatched} pixels ({:.2}%) differ by more than {TOLERANCE}, max difference {max_difference}",
+                100.0 * ratio
+            ),
+            diff,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+fn golden_images() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = vec![];
+
+    for shader in RustGPUShader::iter() {
+        for preset in PRESETS {
+            let Some(actual) = render_preset(shader, preset) else {
+                continue;
+            };
+            let name = format!("{shader}-{}", preset.name);
+            let path = golden_dir().join(format!("{name}.png"));
+
+            if update {
+                std::fs::create_dir_all(golden_dir()).unwrap();
+                actual.save(&path).unwrap();
+                eprintln!("Wrote reference image {}", path.display());
+                continue;
+            }
+            if !path.exists() {
+                failures.push(format!(
+                    "{name}: missing reference {}, run with UPDATE_GOLDEN=1 to create it",
+                    path.display()
+                ));
+                continue;
+            }
+
+            let expected = image::open(&path).unwrap().into_rgba8();
+            if let Err((message, diff)) = compare(&expected, &actual) {
+                std::fs::create_dir_all(failure_dir()).unwrap();
+                let actual_path = failure_dir().join(format!("{name}.actual.png"));
+                let diff_path = failure_dir().join(format!("{name}.diff.png"));
+                actual.save(&actual_path).unwrap();
+                diff.save(&diff_path).unwrap();
+                failures.push(format!("{name}: {message} (see {})", diff_path.display()));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Golden images differ:\n{}",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,207 @@
+//! Reads the dep-info (`.d`) files the shader builds leave next to their
+//! artifacts, to find every source file a shader was built from, including
+//! those of path dependencies outside this repository.
+//!
+//! rust-gpu passes `-Zbinary-dep-depinfo`, so a crate's dep-info lists the
+//! rlibs of its dependencies, whose own dep-info is read in turn. Sources of
+//! registry and git dependencies and of the toolchain are left out: they only
+//! change along with `Cargo.lock` or `rust-toolchain.toml`, and there are far
+//! too many of them to hash or watch.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Source files of the build of `crate_name` that produced `artifact`, or
+/// `None` if its dep-info can't be found.
+pub fn sources(artifact: &Path, crate_name: &str) -> Option<Vec<PathBuf>> {
+    let dep_info = find(artifact, crate_name)?;
+    let excluded = excluded_dirs();
+    let mut visited = HashSet::new();
+    let mut sources = vec![];
+    collect(&dep_info, &excluded, &mut visited, &mut sources);
+    sources.sort();
+    sources.dedup();
+    Some(sources)
+}
+
+/// The newest dep-info named after `crate_name` in the directories above
+/// `artifact`, which covers both the artifact's own directory and the
+/// profile directory cargo copies the final artifacts to.
+fn find(artifact: &Path, crate_name: &str) -> Option<PathBuf> {
+    artifact
+        .ancestors()
+        .skip(1)
+        .take(3)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name();
+            let stem = name.to_str()?.strip_suffix(".d")?;
+            let stem = stem.strip_prefix("lib").unwrap_or(stem);
+            let rest = stem.strip_prefix(crate_name)?;
+            if !(rest.is_empty() || rest.starts_with(['-', '.'])) {
+                return None;
+            }
+            Some((entry.metadata().ok()?.modified().ok()?, entry.path()))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+fn collect(
+    dep_info: &Path,
+    excluded: &[PathBuf],
+    visited: &mut HashSet<PathBuf>,
+    sources: &mut Vec<PathBuf>,
+) {
+    if !visited.insert(dep_info.to_owned()) {
+        return;
+    }
+    let Ok(contents) = fs::read_to_string(dep_info) else {
+        return;
+    };
+    for path in parse(&contents) {
+        if excluded.iter().any(|dir| path.starts_with(dir)) {
+            continue;
+        }
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("rlib" | "rmeta") => {
+                // rustc names the dep-info after the crate, without the `lib` prefix
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let stem = stem.strip_prefix("lib").unwrap_or(&stem);
+                let dep_info = path.with_file_name(format!("{stem}.d"));
+                collect(&dep_info, excluded, visited, sources)
+            }
+            _ => sources.push(path),
+        }
+    }
+}
+
+/// The prerequisites of every rule in a Makefile-style dep-info file.
+fn parse(contents: &str) -> Vec<PathBuf> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            // A space after the colon tells it apart from a Windows drive letter
+            let (_target, prerequisites) = line.split_once(": ")?;
+            Some(split_escaped(prerequisites))
+        })
+        .flatten()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Splits at spaces, except for those escaped as `\ `.
+fn split_escaped(s: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => word.push(chars.next().unwrap()),
+            ' ' => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Cargo's and rustup's homes, where registry and git dependencies and the
+/// toolchain sources live.
+fn excluded_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let dir = |var, default| {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .or_else(|| Some(home.as_ref()?.join(default)))
+    };
+    [dir("CARGO_HOME", ".cargo"), dir("RUSTUP_HOME", ".rustup")]
+        .into_iter()
+        .flatten()
+        .map(|dir| fs::canonicalize(&dir).unwrap_or(dir))
+        .collect()
+}
+
+/// Name of the crate at `crate_path` as rustc spells it.
+pub fn crate_name(crate_path: &Path) -> String {
+    crate_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace('-', "_")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let contents = "/t/release/deps/sdfs_2d-0a1b.d: /w/shaders/sdfs-2d/src/lib.rs /w/my\\ shared/src/lib.rs /t/release/deps/libshared-2c3d.rlib\n\
+                        \n\
+                        /w/shaders/sdfs-2d/src/lib.rs:\n\
+                        C:\\w\\lib.d: C:\\w\\src\\lib.rs\n";
+        assert_eq!(
+            parse(contents),
+            [
+                "/w/shaders/sdfs-2d/src/lib.rs",
+                "/w/my shared/src/lib.rs",
+                "/t/release/deps/libshared-2c3d.rlib",
+                "C:\\w\\src\\lib.rs",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_sources() {
+        let dir = std::env::temp_dir().join(format!("dep-info-test-{}", std::process::id()));
+        let deps = dir.join("release").join("deps");
+        fs::create_dir_all(&deps).unwrap();
+        let write = |name: &str, contents: String| fs::write(deps.join(name), contents).unwrap();
+        let rlib = deps.join("libshared-2c3d.rlib");
+        write(
+            "sdfs_2d-0a1b.d",
+            format!("x: /w/sdfs-2d/src/lib.rs {}\n", rlib.display()),
+        );
+        write(
+            "shared-2c3d.d",
+            "x: /w/shared/src/lib.rs /w/shared/src/sdf.rs\n".to_string(),
+        );
+        // Another crate whose name starts with the same words
+        write("sdfs_2d_extra-4e5f.d", "x: /w/extra.rs\n".to_string());
+
+        let sources = sources(&deps.join("sdfs_2d.spv"), "sdfs_2d");
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            sources,
+            Some(
+                [
+                    "/w/sdfs-2d/src/lib.rs",
+                    "/w/shared/src/lib.rs",
+                    "/w/shared/src/sdf.rs",
+                ]
+                .map(PathBuf::from)
+                .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_crate_name() {
+        assert_eq!(crate_name(Path::new("/w/shaders/sdfs-2d")), "sdfs_2d");
+    }
+}
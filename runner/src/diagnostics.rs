@@ -0,0 +1,102 @@
+//! Compiler messages for shader crates that failed to build.
+//!
+//! A failed crate is checked again on the host with JSON output to get the
+//! messages and their locations. Errors that only rust-gpu's codegen reports
+//! are shown as the build's output instead.
+
+use crate::{
+    shader::{crate_path, BuildError},
+    RustGPUShader,
+};
+use serde::Deserialize;
+use std::{fmt::Display, path::PathBuf, process::Command};
+
+#[derive(Clone)]
+pub struct Diagnostic {
+    /// `file:line:column` of the primary span
+    pub location: Option<String>,
+    /// The message as rustc prints it, including the source snippet
+    pub rendered: String,
+}
+
+impl Diagnostic {
+    pub fn new(rendered: impl Display) -> Self {
+        Self {
+            location: None,
+            rendered: rendered.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    spans: Vec<Span>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+pub fn collect(shader: RustGPUShader, error: &BuildError) -> Vec<Diagnostic> {
+    let mut diagnostics = check(shader).unwrap_or_else(|err| {
+        vec![Diagnostic::new(format!(
+            "Failed to run cargo check for diagnostics: {err}"
+        ))]
+    });
+    if diagnostics.is_empty() {
+        // Errors from rust-gpu's codegen only show up in the real build
+        diagnostics.push(Diagnostic::new(format!(
+            "{}\n\n{}",
+            error.message, error.output
+        )));
+    }
+    diagnostics
+}
+
+fn check(shader: RustGPUShader) -> std::io::Result<Vec<Diagnostic>> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    // A separate target directory, so the check doesn't invalidate the runner's build
+    let target_dir = PathBuf::from(env!("OUT_DIR")).join("diagnostics");
+    let output = Command::new(cargo)
+        .arg("check")
+        .arg("--manifest-path")
+        .arg(crate_path(shader).join("Cargo.toml"))
+        .arg("--message-format=json")
+        .arg("--target-dir")
+        .arg(target_dir)
+        .output()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|message| message.reason == "compiler-message")
+        .filter_map(|message| message.message)
+        .filter(|message| message.level == "error" && !message.spans.is_empty())
+        .map(|message| Diagnostic {
+            location: message
+                .spans
+                .iter()
+                .find(|span| span.is_primary)
+                .map(|span| {
+                    format!(
+                        "{}:{}:{}",
+                        span.file_name, span.line_start, span.column_start
+                    )
+                }),
+            rendered: message.rendered.unwrap_or(message.message),
+        })
+        .collect())
+}
@@ -0,0 +1,167 @@
+//! Offscreen fragment passes whose output is fed back into the next frame,
+//! like Shadertoy's Buffer A to D.
+//!
+//! If [`Controller::feedback_passes`] returns `n`, the entry points
+//! `main_fs_a`, `main_fs_b`, ... render in order into offscreen textures
+//! before `main_fs` draws to the window. Every pass sees buffer `i` at
+//! `descriptor_set = 2, binding = 2 * i`, with a sampler at
+//! `binding = 2 * i + 1`. Buffers that already ran this frame show this
+//! frame's output, the others still show the previous frame's.
+//!
+//! [`Controller::feedback_passes`]: crate::controller::Controller::feedback_passes
+
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+const ENTRY_POINTS: [&str; 4] = ["main_fs_a", "main_fs_b", "main_fs_c", "main_fs_d"];
+pub const MAX_PASSES: usize = ENTRY_POINTS.len();
+
+pub fn entry_point(pass: usize) -> &'static str {
+    ENTRY_POINTS[pass]
+}
+
+pub struct FeedbackBuffers {
+    pub layout: wgpu::BindGroupLayout,
+    count: usize,
+    size: (u32, u32),
+    /// Two textures per pass, one written this frame and one from the last
+    views: Vec<[wgpu::TextureView; 2]>,
+    sampler: wgpu::Sampler,
+    /// `bind_groups[parity][pass]`, where pass `count` is `main_fs`
+    bind_groups: [Vec<wgpu::BindGroup>; 2],
+    parity: usize,
+}
+
+impl FeedbackBuffers {
+    /// Passes beyond [`MAX_PASSES`] are left out, so their entry points are
+    /// never looked up.
+    pub fn new(device: &wgpu::Device, count: usize) -> Self {
+        if count > MAX_PASSES {
+            eprintln!(
+                "At most {MAX_PASSES} feedback passes are supported, ignoring the other {}",
+                count - MAX_PASSES
+            );
+        }
+        let count = count.min(MAX_PASSES);
+        let entries = (0..count as u32)
+            .flat_map(|i| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Feedback Bind Group Layout"),
+            entries: &entries,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Feedback Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            layout,
+            count,
+            size: (0, 0),
+            views: vec![],
+            sampler,
+            bind_groups: [vec![], vec![]],
+            parity: 0,
+        }
+    }
+
+    /// Number of passes before `main_fs`
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Recreates the textures if the output size changed, which clears them.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.size == (width, height) {
+            return;
+        }
+        self.size = (width, height);
+        let create_view = |i| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&format!("Feedback Buffer {i}")),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        self.views = (0..self.count)
+            .map(|i| [create_view(i), create_view(i)])
+            .collect();
+        self.bind_groups = [0, 1].map(|parity| {
+            (0..=self.count)
+                .map(|pass| {
+                    let entries = self
+                        .views
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(i, views)| {
+                            // Earlier passes have written `parity` already
+                            let view = &views[if i < pass { parity } else { 1 - parity }];
+                            [
+                                wgpu::BindGroupEntry {
+                                    binding: 2 * i as u32,
+                                    resource: wgpu::BindingResource::TextureView(view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 2 * i as u32 + 1,
+                                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                                },
+                            ]
+                        })
+                        .collect::<Vec<_>>();
+                    device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("Feedback Bind Group"),
+                        layout: &self.layout,
+                        entries: &entries,
+                    })
+                })
+                .collect()
+        });
+    }
+
+    /// The texture `pass` renders into this frame
+    pub fn target(&self, pass: usize) -> &wgpu::TextureView {
+        &self.views[pass][self.parity]
+    }
+
+    /// The buffers as seen by `pass`, or by `main_fs` for `pass == count`
+    pub fn bind_group(&self, pass: usize) -> &wgpu::BindGroup {
+        &self.bind_groups[self.parity][pass]
+    }
+
+    /// Makes this frame's output the previous frame's.
+    pub fn swap(&mut self) {
+        self.parity = 1 - self.parity;
+    }
+}
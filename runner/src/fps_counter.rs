@@ -1,19 +1,68 @@
+use crate::{profiler::PassTimings, RustGPUShader};
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 
+/// Frames the statistics cover unless `--stats-window` says otherwise
+pub const DEFAULT_WINDOW: usize = 240;
+
+#[derive(Clone, Copy)]
+pub struct FrameTime {
+    /// Time since the previous frame started
+    pub frame: Duration,
+    /// `None` until the first timings have been read back
+    pub passes: Option<PassTimings>,
+}
+
+/// Frame time statistics over the window, in milliseconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    pub min: f32,
+    pub mean: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+/// Counts frames per second and keeps the frame times of the active shader
+/// for the graph, statistics and CSV export.
 pub struct FpsCounter {
     frames: VecDeque<Instant>,
+    /// The latest `window` frames of `shader`, oldest first
+    history: VecDeque<FrameTime>,
+    window: usize,
+    shader: Option<RustGPUShader>,
+    /// Index of the first frame in `history` since `shader` became active
+    first_frame: usize,
 }
 
 impl FpsCounter {
     pub fn new() -> Self {
         Self {
             frames: VecDeque::default(),
+            history: VecDeque::default(),
+            window: DEFAULT_WINDOW,
+            shader: None,
+            first_frame: 0,
         }
     }
 
-    pub fn tick(&mut self) -> usize {
+    /// Records a frame of `shader`, discarding the history of the previous
+    /// shader when it changes.
+    pub fn tick(&mut self, shader: RustGPUShader, passes: Option<PassTimings>) {
         let now = Instant::now();
+        if self.shader != Some(shader) {
+            self.shader = Some(shader);
+            self.history.clear();
+            self.first_frame = 0;
+        } else if let Some(&previous) = self.frames.back() {
+            self.history.push_back(FrameTime {
+                frame: now - previous,
+                passes,
+            });
+            self.truncate();
+        }
         self.frames.push_back(now);
         let one_second_from_now = now - Duration::from_secs(1);
 
@@ -24,8 +73,165 @@ impl FpsCounter {
         {
             self.frames.pop_front();
         }
+    }
 
+    /// Frames started in the last second
+    pub fn fps(&self) -> usize {
         self.frames.len()
     }
+
+    pub fn history(&self) -> &VecDeque<FrameTime> {
+        &self.history
+    }
+
+    /// Number of frames the statistics and export cover
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.history.len() > self.window {
+            self.history.pop_front();
+            self.first_frame += 1;
+        }
+    }
+
+    /// `None` until a frame has been recorded
+    pub fn stats(&self) -> Option<FrameStats> {
+        let mut times = self.frame_times();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_by(f32::total_cmp);
+        // Nearest-rank percentile, the smallest time that at least `p`% of
+        // the frames don't exceed
+        let percentile = |p: f32| {
+            let rank = (p * times.len() as f32 / 100.0).ceil() as usize;
+            times[rank.clamp(1, times.len()) - 1]
+        };
+        Some(FrameStats {
+            min: times[0],
+            mean: times.iter().sum::<f32>() / times.len() as f32,
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: times[times.len() - 1],
+        })
+    }
+
+    /// Frame counts in `bins` equal ranges from `stats.min` to `stats.max`
+    pub fn histogram(&self, stats: FrameStats, bins: usize) -> Vec<usize> {
+        let mut counts = vec![0; bins];
+        let width = (stats.max - stats.min) / bins as f32;
+        for time in self.frame_times() {
+            let bin = if width > 0.0 {
+                ((time - stats.min) / width) as usize
+            } else {
+                0
+            };
+            counts[bin.min(bins - 1)] += 1;
+        }
+        counts
+    }
+
+    fn frame_times(&self) -> Vec<f32> {
+        self.history.iter().map(|time| ms(time.frame)).collect()
+    }
+
+    /// Writes one row per frame of the history, with the pass timings left
+    /// empty until they have been read back.
+    pub fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "frame,frame_ms,shader_ms,ui_ms,present_ms,timer")?;
+        for (index, time) in self.history.iter().enumerate() {
+            write!(
+                writer,
+                "{},{:.4},",
+                self.first_frame + index,
+                ms(time.frame)
+            )?;
+            match time.passes {
+                Some(passes) => writeln!(
+                    writer,
+                    "{:.4},{:.4},{:.4},{}",
+                    ms(passes.shader),
+                    ms(passes.ui),
+                    ms(passes.present),
+                    if passes.gpu { "gpu" } else { "cpu" }
+                )?,
+                None => writeln!(writer, ",,,")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the history as `frame-times/<shader>-<unix time>.csv` and
+    /// returns its path.
+    pub fn export(&self) -> Result<PathBuf, String> {
+        let shader = self.shader.ok_or("No frames have been recorded")?;
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("frame-times");
+        std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|err| err.to_string())?
+            .as_secs();
+        let path = dir.join(format!("{shader}-{seconds}.csv"));
+        let file = std::fs::File::create(&path).map_err(|err| err.to_string())?;
+        self.write_csv(std::io::BufWriter::new(file))
+            .map_err(|err| err.to_string())?;
+        Ok(path)
+    }
 }
 
+fn ms(duration: Duration) -> f32 {
+    (duration.as_nanos() as f64 / 1e6) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn counter(frame_ms: &[u64]) -> FpsCounter {
+        let mut counter = FpsCounter::new();
+        counter.history = frame_ms
+            .iter()
+            .map(|&ms| FrameTime {
+                frame: Duration::from_millis(ms),
+                passes: None,
+            })
+            .collect();
+        counter
+    }
+
+    #[test]
+    fn test_stats() {
+        let counter = counter(&(1..=100).collect::<Vec<_>>());
+        assert_eq!(
+            counter.stats(),
+            Some(FrameStats {
+                min: 1.0,
+                mean: 50.5,
+                p95: 95.0,
+                p99: 99.0,
+                max: 100.0,
+            })
+        );
+        assert_eq!(
+            counter.histogram(counter.stats().unwrap(), 4),
+            [25, 25, 25, 25]
+        );
+    }
+
+    #[test]
+    fn test_window() {
+        let mut counter = counter(&[5, 1, 2, 3]);
+        counter.set_window(2);
+        assert_eq!(counter.stats().map(|stats| stats.max), Some(3.0));
+        assert_eq!(counter.first_frame, 2);
+    }
+}
@@ -0,0 +1,204 @@
+use glam::{vec2, Vec2};
+use std::collections::BTreeMap;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{Touch, TouchPhase},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Gesture {
+    /// A single finger acts as the left mouse button
+    Press(PhysicalPosition<f64>),
+    Move(PhysicalPosition<f64>),
+    Release,
+    /// Two fingers moved: the midpoint by `pan` pixels, the distance between
+    /// them by a factor of `scale`, and their angle by `rotation` radians
+    /// counterclockwise.
+    TwoFinger {
+        pan: Vec2,
+        scale: f32,
+        rotation: f32,
+    },
+}
+
+/// Turns raw touch points into mouse emulation and two-finger gestures.
+pub struct Touches {
+    active: BTreeMap<u64, Vec2>,
+    pressed: bool,
+}
+
+impl Touches {
+    pub fn new() -> Self {
+        Self {
+            active: BTreeMap::new(),
+            pressed: false,
+        }
+    }
+
+    pub fn touch(&mut self, touch: Touch) -> Option<Gesture> {
+        let position = vec2(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.active.insert(touch.id, position);
+                match self.active.len() {
+                    1 => self
+                        .set_pressed(true)
+                        .then_some(Gesture::Press(touch.location)),
+                    // A second finger turns the drag into a gesture
+                    _ => self.set_pressed(false).then_some(Gesture::Release),
+                }
+            }
+            TouchPhase::Moved => {
+                let before = self.pair();
+                *self.active.get_mut(&touch.id)? = position;
+                match (before, self.pair()) {
+                    (Some((a0, b0)), Some((a1, b1))) => {
+                        // Angles are measured with y up
+                        let flip = vec2(1.0, -1.0);
+                        let (d0, d1) = ((b0 - a0) * flip, (b1 - a1) * flip);
+                        if d0 == Vec2::ZERO || d1 == Vec2::ZERO {
+                            return None;
+                        }
+                        Some(Gesture::TwoFinger {
+                            pan: (a1 + b1 - a0 - b0) / 2.0,
+                            scale: d1.length() / d0.length(),
+                            rotation: d0.angle_between(d1),
+                        })
+                    }
+                    _ if self.active.len() == 1 => Some(Gesture::Move(touch.location)),
+                    _ => None,
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.active.remove(&touch.id)?;
+                self.set_pressed(false).then_some(Gesture::Release)
+            }
+        }
+    }
+
+    /// Returns whether the emulated mouse button changed.
+    fn set_pressed(&mut self, pressed: bool) -> bool {
+        std::mem::replace(&mut self.pressed, pressed) != pressed
+    }
+
+    fn pair(&self) -> Option<(Vec2, Vec2)> {
+        match self.active.values().collect::<Vec<_>>()[..] {
+            [a, b] => Some((*a, *b)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+    use winit::event::DeviceId;
+
+    fn touch(touches: &mut Touches, id: u64, phase: TouchPhase, x: f64, y: f64) -> Option<Gesture> {
+        touches.touch(Touch {
+            // SAFETY: only compared against other ids, never passed to winit
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None,
+            id,
+        })
+    }
+
+    fn two_finger(gesture: Option<Gesture>) -> (Vec2, f32, f32) {
+        match gesture {
+            Some(Gesture::TwoFinger {
+                pan,
+                scale,
+                rotation,
+            }) => (pan, scale, rotation),
+            gesture => panic!("expected a two-finger gesture, got {gesture:?}"),
+        }
+    }
+
+    #[test]
+    fn test_single_finger() {
+        let mut touches = Touches::new();
+        assert_eq!(
+            touch(&mut touches, 0, TouchPhase::Started, 10.0, 20.0),
+            Some(Gesture::Press(PhysicalPosition::new(10.0, 20.0)))
+        );
+        assert_eq!(
+            touch(&mut touches, 0, TouchPhase::Moved, 15.0, 25.0),
+            Some(Gesture::Move(PhysicalPosition::new(15.0, 25.0)))
+        );
+        assert_eq!(
+            touch(&mut touches, 0, TouchPhase::Ended, 15.0, 25.0),
+            Some(Gesture::Release)
+        );
+        // Unknown touches are ignored
+        assert_eq!(touch(&mut touches, 1, TouchPhase::Moved, 0.0, 0.0), None);
+        assert_eq!(
+            touch(&mut touches, 1, TouchPhase::Cancelled, 0.0, 0.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_second_finger_releases() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 0, TouchPhase::Started, 0.0, 0.0);
+        assert_eq!(
+            touch(&mut touches, 1, TouchPhase::Started, 10.0, 0.0),
+            Some(Gesture::Release)
+        );
+        // Lifting one finger of the pair doesn't press or release again
+        assert_eq!(touch(&mut touches, 1, TouchPhase::Ended, 10.0, 0.0), None);
+        assert_eq!(touch(&mut touches, 0, TouchPhase::Ended, 0.0, 0.0), None);
+        assert_eq!(
+            touch(&mut touches, 2, TouchPhase::Started, 5.0, 5.0),
+            Some(Gesture::Press(PhysicalPosition::new(5.0, 5.0)))
+        );
+    }
+
+    #[test]
+    fn test_pan() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 0, TouchPhase::Started, 0.0, 0.0);
+        touch(&mut touches, 1, TouchPhase::Started, 10.0, 0.0);
+        let (pan, scale, rotation) =
+            two_finger(touch(&mut touches, 1, TouchPhase::Moved, 14.0, 6.0));
+        assert_eq!(pan, vec2(2.0, 3.0));
+        assert!((scale - 232.0_f32.sqrt() / 10.0).abs() < 1e-6);
+        assert!(rotation < 0.0, "y down on screen is clockwise");
+    }
+
+    #[test]
+    fn test_pinch() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 0, TouchPhase::Started, 10.0, 10.0);
+        touch(&mut touches, 1, TouchPhase::Started, 20.0, 10.0);
+        let (pan, scale, rotation) =
+            two_finger(touch(&mut touches, 1, TouchPhase::Moved, 30.0, 10.0));
+        assert_eq!(pan, vec2(5.0, 0.0));
+        assert_eq!(scale, 2.0);
+        assert_eq!(rotation, 0.0);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 0, TouchPhase::Started, 0.0, 0.0);
+        touch(&mut touches, 1, TouchPhase::Started, 10.0, 0.0);
+        // Moving the second finger up the screen turns counterclockwise
+        let (_, scale, rotation) =
+            two_finger(touch(&mut touches, 1, TouchPhase::Moved, 0.0, -10.0));
+        assert!((scale - 1.0).abs() < 1e-6);
+        assert!((rotation - FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_coincident_fingers() {
+        let mut touches = Touches::new();
+        touch(&mut touches, 0, TouchPhase::Started, 5.0, 5.0);
+        touch(&mut touches, 1, TouchPhase::Started, 10.0, 5.0);
+        // No angle or scale between fingers at the same point
+        assert_eq!(touch(&mut touches, 1, TouchPhase::Moved, 5.0, 5.0), None);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    context::GraphicsContext,
+    controller::{new_controller, Controller},
+    cpu,
+    render_pass::RenderPass,
+    shader, Options, RustGPUShader,
+};
+use std::path::Path;
+use winit::dpi::PhysicalSize;
+
+pub fn screenshot(options: &Options, path: &Path) {
+    env_logger::init();
+
+    let mut controller = new_controller(options.shader, options.size);
+    let mut renderer = Renderer::new(options, &mut *controller);
+    controller.update(options.time);
+
+    save(&renderer.render(&mut *controller), path);
+}
+
+/// Renders `options.frames` frames spaced `options.dt` apart. Time is derived
+/// from the frame index, so the result doesn't depend on how long a frame takes.
+pub fn export(options: &Options, dir: &Path) {
+    env_logger::init();
+
+    std::fs::create_dir_all(dir)
+        .unwrap_or_else(|err| panic!("Failed to create {}: {err}", dir.display()));
+
+    let mut controller = new_controller(options.shader, options.size);
+    let mut renderer = Renderer::new(options, &mut *controller);
+    for frame in 0..options.frames {
+        controller.update(options.time + frame as f32 * options.dt);
+        save(
+            &renderer.render(&mut *controller),
+            &dir.join(format!("{frame:05}.png")),
+        );
+    }
+}
+
+fn save(image: &image::RgbaImage, path: &Path) {
+    image
+        .save(path)
+        .unwrap_or_else(|err| panic!("Failed to save {}: {err}", path.display()));
+}
+
+enum Renderer {
+    Cpu {
+        shader: RustGPUShader,
+        size: PhysicalSize<u32>,
+    },
+    Gpu {
+        ctx: GraphicsContext,
+        rpass: RenderPass,
+    },
+}
+
+impl Renderer {
+    /// Also resizes `controller` to the size it renders at.
+    fn new(options: &Options, controller: &mut dyn Controller) -> Self {
+        if options.cpu {
+            Self::Cpu {
+                shader: options.shader,
+                size: options.size,
+            }
+        } else {
+            let (compiled_shader_modules, _) = shader::compile(options, options.shader)
+                .unwrap_or_else(|err| panic!("Failed to compile {}: {err}", options.shader));
+            let ctx = futures::executor::block_on(GraphicsContext::new_headless(options));
+            let mut rpass = RenderPass::new(&ctx, options.clone());
+            rpass
+                .new_module(&ctx, &compiled_shader_modules, controller)
+                .unwrap_or_else(|err| panic!("Failed to create pipeline: {err}"));
+            controller.resize(rpass.render_size(&ctx, controller));
+            Self::Gpu { ctx, rpass }
+        }
+    }
+
+    fn render(&mut self, controller: &mut dyn Controller) -> image::RgbaImage {
+        match self {
+            Self::Cpu { shader, size } => cpu::render(*shader, controller.push_constants(), *size)
+                .unwrap_or_else(|| panic!("{shader} cannot be rendered on the CPU")),
+            Self::Gpu { ctx, rpass } => render_to_image(ctx, rpass, controller),
+        }
+    }
+}
+
+fn render_to_image(
+    ctx: &GraphicsContext,
+    rpass: &mut RenderPass,
+    controller: &mut dyn Controller,
+) -> image::RgbaImage {
+    let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width: ctx.config.width,
+            height: ctx.config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ctx.config.format,
+        usage: ctx.config.usage,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    rpass.render_shader(ctx, &view, controller);
+
+    read_texture(ctx, &texture)
+}
+
+/// Copies an `Rgba8` texture back to the host.
+fn read_texture(ctx: &GraphicsContext, texture: &wgpu::Texture) -> image::RgbaImage {
+    let width = texture.width();
+    let height = texture.height();
+    let unpadded_bytes_per_row = width * 4;
+    // Rows in the staging buffer must be aligned to 256 bytes
+    let padded_bytes_per_row = (unpadded_bytes_per_row + wgpu::COPY_BYTES_PER_ROW_ALIGNMENT - 1)
+        / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Failed to map readback buffer")
+    });
+    ctx.device.poll(wgpu::Maintain::Wait);
+
+    let data = slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+        .copied()
+        .collect();
+
+    image::RgbaImage::from_raw(width, height, data).unwrap()
+}
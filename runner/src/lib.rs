@@ -1,17 +1,41 @@
+use blit::{Tonemapper, Upscaling};
+use std::path::PathBuf;
 use structopt::StructOpt;
 use strum::{Display, EnumIter, EnumString};
+use winit::dpi::PhysicalSize;
 
 mod app;
+mod bindings;
+mod blit;
+mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+mod compiler;
 mod context;
 mod controller;
+#[cfg(not(target_arch = "wasm32"))]
+mod cpu;
+#[cfg(not(target_arch = "wasm32"))]
+mod dep_info;
+#[cfg(not(target_arch = "wasm32"))]
+mod diagnostics;
+mod feedback;
 mod fps_counter;
+mod gestures;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod profiler;
 mod render_pass;
 mod shader;
 mod shaders;
+#[cfg(not(target_arch = "wasm32"))]
+mod spirv_cache;
 mod state;
+mod targets;
 mod ui;
 mod window;
 mod model;
+mod navigation;
+mod presets;
 mod camera;
 mod texture;
 
@@ -26,8 +50,12 @@ pub enum RustGPUShader {
     SDFs3D,
     HydrogenWavefunction,
     SphericalHarmonics,
-    Gaussian
+    Gaussian,
     SphericalHarmonicsShape,
+    Voronoi,
+    Lens,
+    GameOfLife,
+    Trails,
 }
 
 #[derive(StructOpt, Clone)]
@@ -38,10 +66,187 @@ pub struct Options {
 
     #[structopt(long)]
     force_spirv_passthru: bool,
+
+    /// Render a single frame to this PNG file instead of opening a window
+    #[structopt(long, parse(from_os_str))]
+    screenshot: Option<PathBuf>,
+
+    /// Render an animation into numbered PNG files in this directory instead of opening a window
+    #[structopt(long, parse(from_os_str))]
+    export: Option<PathBuf>,
+
+    /// Number of frames to export
+    #[structopt(long, default_value = "60")]
+    frames: u32,
+
+    /// Time in seconds between exported frames
+    #[structopt(long, default_value = "0.016666668")]
+    dt: f32,
+
+    /// Resolution of the screenshot or exported frames, e.g. 1920x1080
+    #[structopt(long, default_value = "1280x720", parse(try_from_str = parse_size))]
+    size: PhysicalSize<u32>,
+
+    /// Time in seconds passed to the shader when taking a screenshot, or of the first exported frame
+    #[structopt(long, default_value = "0")]
+    time: f32,
+
+    /// Evaluate the fragment shader on the CPU instead of the GPU when rendering headless
+    #[structopt(long)]
+    cpu: bool,
+
+    /// Comma-separated graphics APIs to use: vulkan, metal, dx12, dx11, gl, primary or all.
+    /// Defaults to WGPU_BACKEND, or Vulkan and Metal
+    #[structopt(long, parse(try_from_str = parse_backends))]
+    backend: Option<wgpu::Backends>,
+
+    /// Use the first adapter whose name contains this, ignoring case, e.g. "nvidia" or "llvmpipe"
+    #[structopt(long)]
+    adapter: Option<String>,
+
+    /// Adapter to prefer when `--adapter` isn't given: none, low or high.
+    /// Defaults to WGPU_POWER_PREF
+    #[structopt(long, parse(try_from_str = parse_power_preference))]
+    power_preference: Option<wgpu::PowerPreference>,
+
+    /// Print the available adapters, including GL and software ones, and exit
+    #[structopt(long)]
+    list_adapters: bool,
+
+    /// How frames are presented: AutoVsync, AutoNoVsync, Fifo, FifoRelaxed, Mailbox or Immediate
+    #[structopt(long, default_value = "AutoVsync", parse(try_from_str = parse_present_mode))]
+    present_mode: wgpu::PresentMode,
+
+    /// MSAA samples per pixel for shaders that draw a mesh: 1, 2, 4 or 8
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_msaa))]
+    msaa: u32,
+
+    /// Resolution of fullscreen shaders in percent of the window or `--size`, from 25 to 200
+    #[structopt(long, default_value = "100", parse(try_from_str = parse_render_scale))]
+    render_scale: u32,
+
+    /// Filter that scales the shader's output to the window: Bilinear or Nearest
+    #[structopt(long, default_value = "Bilinear")]
+    upscaling: Upscaling,
+
+    /// Render fullscreen shaders at N×N pixels per output pixel, from 1 to 4
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_supersample))]
+    supersample: u32,
+
+    /// Curve that maps the shader's HDR output to the screen: None, Reinhard, ACES or AgX
+    #[structopt(long, default_value = "None")]
+    tonemap: Tonemapper,
+
+    /// Stops of exposure applied before tonemapping
+    #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+    exposure: f32,
+
+    /// Average the frames of fullscreen shaders while the view is static
+    #[structopt(long)]
+    accumulate: bool,
+
+    /// Frames covered by the frame-time statistics in the fps window
+    #[structopt(long, default_value = "240")]
+    stats_window: usize,
+
+    /// Build this shader with rust-gpu, print where the SPIR-V went and exit.
+    /// The runner builds shaders this way to capture the rust-gpu output
+    #[structopt(long, hidden = true)]
+    build_shader: Option<RustGPUShader>,
+}
+
+fn parse_size(s: &str) -> Result<PhysicalSize<u32>, String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {s:?}"))?;
+    let width = width.parse::<u32>().map_err(|err| err.to_string())?;
+    let height = height.parse::<u32>().map_err(|err| err.to_string())?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be non-zero".to_string());
+    }
+    Ok(PhysicalSize::new(width, height))
+}
+
+fn parse_msaa(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>().map_err(|err| err.to_string())? {
+        samples @ (1 | 2 | 4 | 8) => Ok(samples),
+        samples => Err(format!("expected 1, 2, 4 or 8 samples, got {samples}")),
+    }
+}
+
+fn parse_render_scale(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>().map_err(|err| err.to_string())? {
+        percent @ 25..=200 => Ok(percent),
+        percent => Err(format!("expected a percentage from 25 to 200, got {percent}")),
+    }
+}
+
+fn parse_backends(s: &str) -> Result<wgpu::Backends, String> {
+    use wgpu::Backends;
+    s.split(',').try_fold(Backends::empty(), |backends, name| {
+        Ok(backends
+            | match name.trim().to_lowercase().as_str() {
+                "vulkan" | "vk" => Backends::VULKAN,
+                "metal" | "mtl" => Backends::METAL,
+                "dx12" | "d3d12" => Backends::DX12,
+                "dx11" | "d3d11" => Backends::DX11,
+                "gl" | "gles" | "opengl" => Backends::GL,
+                "primary" => Backends::PRIMARY,
+                "all" => Backends::all(),
+                name => return Err(format!("unknown backend {name:?}")),
+            })
+    })
+}
+
+fn parse_power_preference(s: &str) -> Result<wgpu::PowerPreference, String> {
+    match s.to_lowercase().as_str() {
+        "none" => Ok(wgpu::PowerPreference::None),
+        "low" => Ok(wgpu::PowerPreference::LowPower),
+        "high" => Ok(wgpu::PowerPreference::HighPerformance),
+        _ => Err(format!("expected none, low or high, got {s:?}")),
+    }
+}
+
+fn parse_present_mode(s: &str) -> Result<wgpu::PresentMode, String> {
+    use wgpu::PresentMode::*;
+    [AutoVsync, AutoNoVsync, Fifo, FifoRelaxed, Mailbox, Immediate]
+        .into_iter()
+        .find(|mode| format!("{mode:?}").eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("unknown present mode {s:?}"))
+}
+
+fn parse_supersample(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>().map_err(|err| err.to_string())? {
+        factor @ 1..=4 => Ok(factor),
+        factor => Err(format!("expected a factor from 1 to 4, got {factor}")),
+    }
 }
 
 pub fn main() {
     let options: Options = Options::from_args();
 
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(shader) = options.build_shader {
+        shader::build_in_child(&options, shader);
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.list_adapters {
+        context::list_adapters(&options);
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &options.screenshot {
+        headless::screenshot(&options, path);
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(dir) = &options.export {
+        headless::export(&options, dir);
+        return;
+    }
+
     app::start(options);
 }
@@ -0,0 +1,98 @@
+use glam::{vec2, vec3, Vec2, Vec3};
+use std::collections::HashSet;
+use web_time::Instant;
+use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
+
+/// Fly speed in units per second
+const FLY_SPEED: f32 = 2.0;
+/// Speed multiplier while shift is held
+const FLY_FAST: f32 = 4.0;
+/// Pixels panned per arrow key press
+const PAN_STEP: f32 = 40.0;
+
+/// Tracks the held WASD/QE keys for flying through the ray-marched scenes.
+/// Movement follows wall time, so it still works while the clock is paused.
+pub struct FlyCamera {
+    held: HashSet<VirtualKeyCode>,
+    fast: bool,
+    last_update: Option<Instant>,
+}
+
+impl FlyCamera {
+    pub fn new() -> Self {
+        Self {
+            held: HashSet::new(),
+            fast: false,
+            last_update: None,
+        }
+    }
+
+    pub fn keyboard_input(&mut self, input: KeyboardInput) {
+        if let Some(key) = input.virtual_keycode {
+            match input.state {
+                ElementState::Pressed => self.held.insert(key),
+                ElementState::Released => self.held.remove(&key),
+            };
+        }
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.fast = modifiers.shift();
+    }
+
+    /// Forgets the held keys, whose release events will go elsewhere
+    pub fn release_keys(&mut self) {
+        self.held.clear();
+    }
+
+    /// Movement since the previous call in view space: x right, y up, z forward.
+    pub fn translation(&mut self) -> Vec3 {
+        let now = Instant::now();
+        // Cap the step so switching back to the shader doesn't teleport the camera
+        let dt = self
+            .last_update
+            .map_or(0.0, |last| (now - last).as_secs_f32().min(0.1));
+        self.last_update = Some(now);
+
+        let axis = |negative, positive| {
+            self.held.contains(&positive) as i32 as f32
+                - self.held.contains(&negative) as i32 as f32
+        };
+        let direction = vec3(
+            axis(VirtualKeyCode::A, VirtualKeyCode::D),
+            axis(VirtualKeyCode::Q, VirtualKeyCode::E),
+            axis(VirtualKeyCode::S, VirtualKeyCode::W),
+        );
+        let speed = if self.fast {
+            FLY_SPEED * FLY_FAST
+        } else {
+            FLY_SPEED
+        };
+        direction.normalize_or_zero() * speed * dt
+    }
+}
+
+pub enum KeyNavigation {
+    /// Offset of the view in pixels
+    Pan(Vec2),
+    /// Zoom in by this many scroll wheel lines, out if negative
+    Zoom(f32),
+}
+
+/// Arrow keys pan and +/- zoom the 2D views, one step per press or key repeat.
+pub fn key_navigation(input: KeyboardInput) -> Option<KeyNavigation> {
+    if input.state != ElementState::Pressed {
+        return None;
+    }
+    Some(match input.virtual_keycode? {
+        VirtualKeyCode::Left => KeyNavigation::Pan(vec2(-PAN_STEP, 0.0)),
+        VirtualKeyCode::Right => KeyNavigation::Pan(vec2(PAN_STEP, 0.0)),
+        VirtualKeyCode::Up => KeyNavigation::Pan(vec2(0.0, -PAN_STEP)),
+        VirtualKeyCode::Down => KeyNavigation::Pan(vec2(0.0, PAN_STEP)),
+        VirtualKeyCode::Plus | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => {
+            KeyNavigation::Zoom(1.0)
+        }
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => KeyNavigation::Zoom(-1.0),
+        _ => return None,
+    })
+}
@@ -0,0 +1,100 @@
+//! Controller state saved as `.ron` files in `presets/<shader>/`, so
+//! interesting views survive restarts and can be shared through git.
+//!
+//! Controllers keep the state they save in a single [`Preset`] type, so saving
+//! and loading is [`to_ron`] and [`from_ron`] rather than a copy of every field.
+
+use crate::{controller::Controller, RustGPUShader};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// The user-tunable state of a controller
+pub trait Preset: Serialize + DeserializeOwned {
+    /// Rejects a loaded preset the controller can't render, such as one with
+    /// an index out of range, before it replaces the current state
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub fn to_ron(preset: &impl Preset) -> Option<String> {
+    ron::ser::to_string_pretty(preset, Default::default()).ok()
+}
+
+pub fn from_ron<P: Preset>(ron: &str) -> Result<P, String> {
+    let preset: P = ron::from_str(ron).map_err(|err| err.to_string())?;
+    preset.validate()?;
+    Ok(preset)
+}
+
+fn dir(shader: RustGPUShader) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("presets")
+        .join(shader.to_string())
+}
+
+/// The path of the preset `name`, which must be a plain file name so that
+/// it stays inside the shader's presets directory
+fn path(shader: RustGPUShader, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => {
+            Ok(dir(shader).join(format!("{name}.ron")))
+        }
+        _ => Err(format!("Invalid preset name {name:?}")),
+    }
+}
+
+pub fn list(shader: RustGPUShader) -> Vec<String> {
+    let mut names = std::fs::read_dir(dir(shader))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "ron" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+pub fn save(shader: RustGPUShader, name: &str, controller: &dyn Controller) -> Result<(), String> {
+    let path = path(shader, name)?;
+    let preset = controller
+        .preset()
+        .ok_or_else(|| format!("{shader} does not support presets"))?;
+    std::fs::create_dir_all(dir(shader)).map_err(|err| err.to_string())?;
+    std::fs::write(path, preset).map_err(|err| err.to_string())
+}
+
+pub fn load(
+    shader: RustGPUShader,
+    name: &str,
+    controller: &mut dyn Controller,
+) -> Result<(), String> {
+    let preset = std::fs::read_to_string(path(shader, name)?).map_err(|err| err.to_string())?;
+    controller
+        .load_preset(&preset)
+        .map_err(|err| format!("{name}.ron: {err}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path() {
+        let shader = RustGPUShader::Mandelbrot;
+        assert_eq!(
+            path(shader, "deep zoom"),
+            Ok(dir(shader).join("deep zoom.ron"))
+        );
+        for name in ["", ".", "..", "../../x", "a/b", "a\\b", "/etc/passwd", "x/"] {
+            assert!(path(shader, name).is_err(), "{name:?} was accepted");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Times the passes of each frame with timestamp queries when the device
+//! supports them, or on the CPU otherwise.
+//!
+//! GPU timestamps are copied back asynchronously, so their timings lag a few
+//! frames behind and are skipped while the previous copy is still mapping.
+
+use crate::context::GraphicsContext;
+use std::sync::{Arc, Mutex};
+use web_time::{Duration, Instant};
+
+/// Points of a frame that are timed, in the order they are reached
+#[derive(Clone, Copy)]
+pub enum Mark {
+    ShaderStart,
+    ShaderEnd,
+    UiStart,
+    UiEnd,
+}
+
+const MARKS: usize = 4;
+
+/// How long the parts of one frame took
+#[derive(Clone, Copy, Debug)]
+pub struct PassTimings {
+    /// The compute, feedback and shader passes, and the blits after them
+    pub shader: Duration,
+    pub ui: Duration,
+    /// Waiting for the swapchain image and presenting it, always timed on
+    /// the CPU
+    pub present: Duration,
+    /// Whether `shader` and `ui` were measured on the GPU
+    pub gpu: bool,
+}
+
+struct Queries {
+    set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    /// Result of mapping `readback`, set by the callback of `map_async`
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    /// Whether `readback` is being mapped, which keeps new timestamps out
+    pending: bool,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+}
+
+pub struct Profiler {
+    /// `None` without [`wgpu::Features::TIMESTAMP_QUERY`]
+    queries: Option<Queries>,
+    cpu_marks: [Instant; MARKS],
+    present: Duration,
+    timings: Option<PassTimings>,
+}
+
+impl Profiler {
+    pub fn new(ctx: &GraphicsContext) -> Self {
+        let queries = ctx
+            .device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let size = (MARKS * std::mem::size_of::<u64>()) as wgpu::BufferAddress;
+                Queries {
+                    set: ctx.device.create_query_set(&wgpu::QuerySetDescriptor {
+                        label: Some("Timestamp Query Set"),
+                        ty: wgpu::QueryType::Timestamp,
+                        count: MARKS as u32,
+                    }),
+                    resolve: ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Timestamp Resolve Buffer"),
+                        size,
+                        usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                        mapped_at_creation: false,
+                    }),
+                    readback: ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Timestamp Readback Buffer"),
+                        size,
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    }),
+                    mapped: Arc::default(),
+                    pending: false,
+                    period: ctx.queue.get_timestamp_period(),
+                }
+            });
+        Self {
+            queries,
+            cpu_marks: [Instant::now(); MARKS],
+            present: Duration::ZERO,
+            timings: None,
+        }
+    }
+
+    /// Records when `encoder` reaches `mark`, or the CPU does without
+    /// timestamp queries.
+    pub fn mark(&mut self, encoder: &mut wgpu::CommandEncoder, mark: Mark) {
+        self.cpu_marks[mark as usize] = Instant::now();
+        if let Some(queries) = &self.queries {
+            encoder.write_timestamp(&queries.set, mark as u32);
+        }
+    }
+
+    /// Copies the frame's timestamps into the readback buffer, unless it
+    /// still holds earlier ones.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(queries) = &self.queries {
+            if !queries.pending {
+                encoder.resolve_query_set(&queries.set, 0..MARKS as u32, &queries.resolve, 0);
+                encoder.copy_buffer_to_buffer(
+                    &queries.resolve,
+                    0,
+                    &queries.readback,
+                    0,
+                    queries.readback.size(),
+                );
+            }
+        }
+    }
+
+    /// Called once the encoder passed to [`Profiler::resolve`] has been
+    /// submitted and the frame presented.
+    pub fn end_frame(&mut self, present: Duration) {
+        self.present = present;
+        match &mut self.queries {
+            Some(queries) => {
+                if !queries.pending {
+                    queries.pending = true;
+                    let mapped = queries.mapped.clone();
+                    queries
+                        .readback
+                        .slice(..)
+                        .map_async(wgpu::MapMode::Read, move |result| {
+                            *mapped.lock().unwrap() = Some(result);
+                        });
+                }
+            }
+            None => {
+                let elapsed = |start: Mark, end: Mark| {
+                    self.cpu_marks[end as usize] - self.cpu_marks[start as usize]
+                };
+                self.timings = Some(PassTimings {
+                    shader: elapsed(Mark::ShaderStart, Mark::ShaderEnd),
+                    ui: elapsed(Mark::UiStart, Mark::UiEnd),
+                    present,
+                    gpu: false,
+                });
+            }
+        }
+    }
+
+    /// Timings of the latest frame whose timestamps have been read back
+    pub fn timings(&mut self, device: &wgpu::Device) -> Option<PassTimings> {
+        let Some(queries) = &mut self.queries else {
+            return self.timings;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        let Some(result) = queries.mapped.lock().unwrap().take() else {
+            return self.timings;
+        };
+        queries.pending = false;
+        if result.is_ok() {
+            let ticks: Vec<u64> =
+                bytemuck::cast_slice(&queries.readback.slice(..).get_mapped_range()).to_vec();
+            queries.readback.unmap();
+            let elapsed = |start: Mark, end: Mark| {
+                let ticks = ticks[end as usize].wrapping_sub(ticks[start as usize]);
+                Duration::from_nanos((ticks as f64 * queries.period as f64) as u64)
+            };
+            self.timings = Some(PassTimings {
+                shader: elapsed(Mark::ShaderStart, Mark::ShaderEnd),
+                ui: elapsed(Mark::UiStart, Mark::UiEnd),
+                present: self.present,
+                gpu: true,
+            });
+        }
+        self.timings
+    }
+}
@@ -1,13 +1,20 @@
 use crate::{
+    bindings::{BindGroup, TextureBindGroup, TextureBinding},
+    blit::{Blit, Tonemapping, Upscaling},
     context::GraphicsContext,
     controller::Controller,
+    feedback::{self, FeedbackBuffers},
     model::Vertex,
+    profiler::{Mark, Profiler},
     shader::CompiledShaderModules,
-    texture::Texture,
+    targets::{TargetSettings, Targets, HDR_FORMAT},
     ui::{Ui, UiState},
     Options,
 };
+use glam::{vec2, Vec2};
+use web_time::Instant;
 use wgpu::{util::DeviceExt, TextureView};
+use winit::dpi::PhysicalSize;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod shaders {
@@ -21,53 +28,217 @@ mod shaders {
     include!(concat!(env!("OUT_DIR"), "/entry_points.rs"));
 }
 
+/// Entry point of the optional compute pass, see [`Controller::dispatch`]
+const COMPUTE_ENTRY_POINT: &str = "main_cs";
+
+/// Frames after which accumulation stops, as further frames hardly change
+/// the half precision average
+const MAX_ACCUMULATED_FRAMES: u32 = 256;
+
 pub struct RenderPass {
-    pipeline_layout: wgpu::PipelineLayout,
-    render_pipeline: wgpu::RenderPipeline,
+    /// `None` until the active shader has been compiled
+    render_pipeline: Option<wgpu::RenderPipeline>,
+    compute_pipeline: Option<wgpu::ComputePipeline>,
+    feedback_pipelines: Vec<wgpu::RenderPipeline>,
     ui_renderer: egui_wgpu::Renderer,
     options: Options,
     buffers: Option<[wgpu::Buffer; 2]>,
+    bind_group: Option<BindGroup>,
+    texture_bind_group: Option<TextureBindGroup>,
+    feedback: Option<FeedbackBuffers>,
+    /// MSAA samples of mesh pipelines
+    sample_count: u32,
+    /// Samples `render_pipeline` was created with
+    pipeline_sample_count: u32,
+    /// Resolution of fullscreen shaders relative to the output, in percent
+    render_scale: u32,
+    upscaling: Upscaling,
+    /// Pixels rendered per scaled pixel along each axis by fullscreen shaders
+    supersampling: u32,
+    tonemapping: Tonemapping,
+    /// Whether fullscreen shaders average their frames while the view is static
+    accumulate: bool,
+    /// Frames averaged into the accumulation target so far
+    accumulated_frames: u32,
+    /// Push constants and bindings of the accumulated frames, which restart
+    /// the average when they change
+    accumulation_key: Vec<u8>,
+    targets: Targets,
+    blit: Blit,
+    profiler: Profiler,
 }
 
 impl RenderPass {
-    pub fn new(
-        ctx: &GraphicsContext,
-        compiled_shader_modules: CompiledShaderModules,
-        options: Options,
-        maybe_buffers: Option<(&[Vertex], &[u32])>,
-    ) -> Self {
-        let pipeline_layout = ctx
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[],
-                push_constant_ranges: &[wgpu::PushConstantRange {
-                    stages: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    range: 0..shared::push_constants::largest_size() as u32,
-                }],
-            });
-
-        let render_pipeline = create_pipeline(
-            &options,
-            &ctx.device,
-            &pipeline_layout,
-            ctx.config.format,
-            compiled_shader_modules,
-            maybe_buffers.is_some(),
-        );
-        let buffers = maybe_create_buffers(ctx, maybe_buffers);
-
+    pub fn new(ctx: &GraphicsContext, options: Options) -> Self {
         let ui_renderer = egui_wgpu::Renderer::new(&ctx.device, ctx.config.format, None, 1);
+        let sample_count = ctx.supported_sample_count(options.msaa);
+        if sample_count != options.msaa {
+            eprintln!(
+                "{}x MSAA is not supported, using {sample_count}x",
+                options.msaa
+            );
+        }
+        let render_scale = options.render_scale;
+        let upscaling = options.upscaling;
+        let supersampling = options.supersample;
+        let tonemapping = Tonemapping {
+            tonemapper: options.tonemap,
+            exposure: options.exposure,
+        };
+        let accumulate = options.accumulate;
+        let blit = Blit::new(&ctx.device, ctx.config.format);
+        let profiler = Profiler::new(ctx);
 
         Self {
-            pipeline_layout,
-            render_pipeline,
+            render_pipeline: None,
+            compute_pipeline: None,
+            feedback_pipelines: vec![],
             ui_renderer,
             options,
-            buffers,
+            buffers: None,
+            bind_group: None,
+            texture_bind_group: None,
+            feedback: None,
+            sample_count,
+            pipeline_sample_count: 1,
+            render_scale,
+            upscaling,
+            supersampling,
+            tonemapping,
+            accumulate,
+            accumulated_frames: 0,
+            accumulation_key: vec![],
+            targets: Targets::default(),
+            blit,
+            profiler,
         }
     }
 
+    /// Recreates the device's resources after it was lost. The pipeline is
+    /// cleared until the next [`RenderPass::new_module`].
+    pub fn new_device(&mut self, ctx: &GraphicsContext) {
+        self.ui_renderer = egui_wgpu::Renderer::new(&ctx.device, ctx.config.format, None, 1);
+        self.sample_count = ctx.supported_sample_count(self.sample_count);
+        self.blit = Blit::new(&ctx.device, ctx.config.format);
+        self.profiler = Profiler::new(ctx);
+        self.targets = Targets::default();
+        self.clear_pipeline();
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Takes effect at the next [`RenderPass::new_module`].
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = sample_count;
+    }
+
+    pub fn render_scale(&self) -> u32 {
+        self.render_scale
+    }
+
+    pub fn set_render_scale(&mut self, percent: u32) {
+        self.render_scale = percent;
+    }
+
+    pub fn upscaling(&self) -> Upscaling {
+        self.upscaling
+    }
+
+    pub fn set_upscaling(&mut self, upscaling: Upscaling) {
+        self.upscaling = upscaling;
+    }
+
+    pub fn supersampling(&self) -> u32 {
+        self.supersampling
+    }
+
+    pub fn set_supersampling(&mut self, supersampling: u32) {
+        self.supersampling = supersampling;
+    }
+
+    pub fn tonemapping(&self) -> Tonemapping {
+        self.tonemapping
+    }
+
+    pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+        self.tonemapping = tonemapping;
+    }
+
+    pub fn accumulate(&self) -> bool {
+        self.accumulate
+    }
+
+    pub fn set_accumulate(&mut self, accumulate: bool) {
+        self.accumulate = accumulate;
+        self.accumulated_frames = 0;
+    }
+
+    /// Only fullscreen shaders that don't evolve on the GPU accumulate
+    fn accumulates(&self, controller: &dyn Controller) -> bool {
+        self.accumulate
+            && controller.buffers().is_none()
+            && controller.dispatch().is_none()
+            && controller.feedback_passes() == 0
+    }
+
+    /// Jitters `controller` for the next accumulated frame, restarting the
+    /// average if its accumulation key or bindings changed since the last one.
+    pub fn prepare_accumulation(&mut self, controller: &mut dyn Controller) {
+        controller.set_jitter(Vec2::ZERO);
+        if !self.accumulates(controller) {
+            return;
+        }
+        let mut key = controller.accumulation_key();
+        for binding in controller.bindings() {
+            key.extend_from_slice(binding.contents);
+        }
+        if key != self.accumulation_key {
+            self.accumulated_frames = 0;
+            self.accumulation_key = key;
+        }
+        // The first frame is left centred, so a moving view looks the same
+        // as without accumulation
+        let index = self.accumulated_frames;
+        if index > 0 {
+            controller.set_jitter(vec2(halton(index, 2), halton(index, 3)) - 0.5);
+        }
+    }
+
+    /// Size `controller` renders at, which differs from the output's when
+    /// a fullscreen shader is scaled or supersampled.
+    pub fn render_size(
+        &self,
+        ctx: &GraphicsContext,
+        controller: &dyn Controller,
+    ) -> PhysicalSize<u32> {
+        let (size, supersampling) = self.scaled_size(ctx, controller);
+        PhysicalSize::new(size.width * supersampling, size.height * supersampling)
+    }
+
+    /// The size that is tonemapped to the output, and the supersampling
+    /// factor on top of it. Meshes use MSAA instead, and both are limited
+    /// by the device's largest texture size.
+    fn scaled_size(
+        &self,
+        ctx: &GraphicsContext,
+        controller: &dyn Controller,
+    ) -> (PhysicalSize<u32>, u32) {
+        let output = PhysicalSize::new(ctx.config.width, ctx.config.height);
+        if controller.buffers().is_some() {
+            return (output, 1);
+        }
+        let max_dimension = ctx.device.limits().max_texture_dimension_2d;
+        let scale = |length: u32| ((length * self.render_scale + 50) / 100).clamp(1, max_dimension);
+        let size = PhysicalSize::new(scale(output.width), scale(output.height));
+        let supersampling = self
+            .supersampling
+            .min(max_dimension / size.width.max(size.height))
+            .max(1);
+        (size, supersampling)
+    }
+
     pub fn render(
         &mut self,
         ctx: &GraphicsContext,
@@ -75,61 +246,175 @@ impl RenderPass {
         ui: &mut Ui,
         ui_state: &mut UiState,
         controller: &mut dyn Controller,
-        depth_texture: Option<&Texture>,
     ) -> Result<(), wgpu::SurfaceError> {
-        let output = match ctx.surface.get_current_texture() {
+        let surface = ctx
+            .surface
+            .as_ref()
+            .expect("Rendering to a window requires a surface");
+        let acquire_start = Instant::now();
+        let output = match surface.get_current_texture() {
             Ok(surface_texture) => surface_texture,
             Err(err) => {
                 eprintln!("get_current_texture error: {err:?}");
                 return match err {
-                    wgpu::SurfaceError::Lost => {
-                        ctx.surface.configure(&ctx.device, &ctx.config);
+                    // The window changed or the surface was lost, e.g. with
+                    // a monitor, and the next frame can use it once reconfigured
+                    wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
+                        ctx.configure_surface();
                         Ok(())
                     }
-                    _ => Err(err),
+                    // The frame is dropped and the next one tried
+                    wgpu::SurfaceError::Timeout => Ok(()),
+                    wgpu::SurfaceError::OutOfMemory => Err(err),
                 };
             }
         };
+        let acquire_time = acquire_start.elapsed();
         let output_view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        self.render_shader(ctx, &output_view, controller, depth_texture);
+        self.render_shader(ctx, &output_view, controller);
+        ui_state.accumulated_frames = self.accumulated_frames;
+        ui_state.pass_timings = self.profiler.timings(&ctx.device);
         self.render_ui(ctx, &output_view, window, ui, ui_state, controller);
 
+        let present_start = Instant::now();
         output.present();
+        self.profiler
+            .end_frame(acquire_time + present_start.elapsed());
 
         Ok(())
     }
 
-    fn render_shader(
+    pub fn render_shader(
         &mut self,
         ctx: &GraphicsContext,
         output_view: &TextureView,
-        controller: &dyn Controller,
-        depth_texture: Option<&Texture>,
+        controller: &mut dyn Controller,
     ) {
+        let (size, supersampling) = self.scaled_size(ctx, controller);
+        let has_buffers = self.buffers.is_some();
+        let recreated = self.targets.prepare(
+            ctx,
+            &self.blit,
+            TargetSettings {
+                width: size.width,
+                height: size.height,
+                filter: self.upscaling.filter(),
+                depth: has_buffers,
+                sample_count: self.pipeline_sample_count,
+                supersampling,
+                accumulate: self.accumulates(controller),
+            },
+        );
+        if recreated {
+            self.accumulated_frames = 0;
+        }
+        let bindings = controller.bindings();
+        if self.layout_changed(controller) {
+            // Nothing is drawn until the pipeline is rebuilt for the new layout
+            self.clear_pipeline();
+        }
+        if let Some(bind_group) = &mut self.bind_group {
+            bind_group.upload(ctx, &bindings);
+        }
         let mut encoder = ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Shader Encoder"),
             });
+        self.profiler.mark(&mut encoder, Mark::ShaderStart);
+        if let (Some(compute_pipeline), Some([x, y, z])) =
+            (&self.compute_pipeline, controller.dispatch())
         {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Shader Compute Pass"),
+            });
+            cpass.set_pipeline(compute_pipeline);
+            cpass.set_push_constants(0, controller.push_constants());
+            if let (Some(bind_group), Some(texture_bind_group)) =
+                (&self.bind_group, &self.texture_bind_group)
+            {
+                cpass.set_bind_group(0, bind_group.bind_group(), &[]);
+                cpass.set_bind_group(1, texture_bind_group.bind_group(), &[]);
+            }
+            cpass.dispatch_workgroups(x, y, z);
+            controller.dispatched();
+        }
+        if let Some(feedback) = &mut self.feedback {
+            feedback.resize(
+                &ctx.device,
+                size.width * supersampling,
+                size.height * supersampling,
+            );
+        }
+        let bind_groups = |pass| {
+            [
+                self.bind_group.as_ref().map(|group| group.bind_group()),
+                self.texture_bind_group
+                    .as_ref()
+                    .map(|group| group.bind_group()),
+                self.feedback
+                    .as_ref()
+                    .map(|feedback| feedback.bind_group(pass)),
+            ]
+            .into_iter()
+            .flatten()
+            .enumerate()
+        };
+        for (pass, pipeline) in self.feedback_pipelines.iter().enumerate() {
+            let feedback = self.feedback.as_ref().unwrap();
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Shader Render Pass"),
+                label: Some("Feedback Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &output_view,
+                    view: feedback.target(pass),
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(if self.buffers.is_some() {
-                            wgpu::Color::BLACK
-                        } else {
-                            wgpu::Color::GREEN
-                        }),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_push_constants(
+                wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                0,
+                controller.push_constants(),
+            );
+            for (index, bind_group) in bind_groups(pass) {
+                rpass.set_bind_group(index as u32, bind_group, &[]);
+            }
+            rpass.draw(0..3, 0..1);
+        }
+        let (hdr, hdr_bind_group) = self.targets.hdr.as_ref().unwrap();
+        // Meshes render into the multisampled texture, which resolves into
+        // `hdr`, and supersampled shaders into the enlarged texture
+        let (view, resolve_target) = match (&self.targets.multisampled, &self.targets.supersampled)
+        {
+            (Some(multisampled), _) => (multisampled, Some(hdr)),
+            (None, Some((supersampled, _))) => (supersampled, None),
+            (None, None) => (hdr, None),
+        };
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shader Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(
+                            if has_buffers || self.render_pipeline.is_none() {
+                                wgpu::Color::BLACK
+                            } else {
+                                wgpu::Color::GREEN
+                            },
+                        ),
                         store: true,
                     },
                 })],
-                depth_stencil_attachment: depth_texture.map(|depth_texture| {
+                depth_stencil_attachment: self.targets.depth.as_ref().map(|depth_texture| {
                     wgpu::RenderPassDepthStencilAttachment {
                         view: &depth_texture.view,
                         depth_ops: Some(wgpu::Operations {
@@ -141,23 +426,55 @@ impl RenderPass {
                 }),
             });
 
-            rpass.set_pipeline(&self.render_pipeline);
-            rpass.set_push_constants(
-                wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                0,
-                controller.push_constants(),
-            );
-            if let Some([vertex_buffer, index_buffer]) = &self.buffers {
-                rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                let num_indices = index_buffer.size() as u32 / std::mem::size_of::<u32>() as u32;
-                rpass.draw_indexed(0..num_indices, 0, 0..1);
-            } else {
-                rpass.draw(0..3, 0..1);
-            };
+            // Until the shader has been compiled the frame is only cleared
+            if let Some(render_pipeline) = &self.render_pipeline {
+                rpass.set_pipeline(render_pipeline);
+                rpass.set_push_constants(
+                    wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    0,
+                    controller.push_constants(),
+                );
+                for (index, bind_group) in bind_groups(self.feedback_pipelines.len()) {
+                    rpass.set_bind_group(index as u32, bind_group, &[]);
+                }
+                if let Some([vertex_buffer, index_buffer]) = &self.buffers {
+                    rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    rpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    let num_indices =
+                        index_buffer.size() as u32 / std::mem::size_of::<u32>() as u32;
+                    rpass.draw_indexed(0..num_indices, 0, 0..1);
+                } else {
+                    rpass.draw(0..3, 0..1);
+                };
+            }
+        }
+        if let Some((_, bind_group)) = &self.targets.supersampled {
+            self.blit
+                .downsample(&mut encoder, bind_group, hdr, supersampling);
         }
+        let tonemap_source = match &self.targets.accumulated {
+            Some((accumulated, bind_group)) => {
+                if self.accumulated_frames < MAX_ACCUMULATED_FRAMES {
+                    self.blit.accumulate(
+                        &mut encoder,
+                        hdr_bind_group,
+                        accumulated,
+                        self.accumulated_frames,
+                    );
+                    self.accumulated_frames += 1;
+                }
+                bind_group
+            }
+            None => hdr_bind_group,
+        };
+        self.blit
+            .tonemap(&mut encoder, tonemap_source, output_view, self.tonemapping);
+        self.profiler.mark(&mut encoder, Mark::ShaderEnd);
 
         ctx.queue.submit(Some(encoder.finish()));
+        if let Some(feedback) = &mut self.feedback {
+            feedback.swap();
+        }
     }
 
     fn render_ui(
@@ -186,6 +503,7 @@ impl RenderPass {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("UI Encoder"),
             });
+        self.profiler.mark(&mut encoder, Mark::UiStart);
 
         self.ui_renderer.update_buffers(
             &ctx.device,
@@ -216,25 +534,135 @@ impl RenderPass {
             self.ui_renderer
                 .render(&mut rpass, &clipped_primitives, &screen_descriptor);
         }
+        self.profiler.mark(&mut encoder, Mark::UiEnd);
+        self.profiler.resolve(&mut encoder);
 
         ctx.queue.submit(Some(encoder.finish()));
     }
 
+    /// Replaces the pipeline, or keeps the current one if `new_module` fails
+    /// validation.
     pub fn new_module(
         &mut self,
         ctx: &GraphicsContext,
-        new_module: CompiledShaderModules,
-        maybe_buffers: Option<(&[Vertex], &[u32])>,
-    ) {
-        self.buffers = maybe_create_buffers(ctx, maybe_buffers);
-        self.render_pipeline = create_pipeline(
+        new_module: &CompiledShaderModules,
+        controller: &dyn Controller,
+    ) -> Result<(), wgpu::Error> {
+        let maybe_buffers = controller.buffers();
+        let sample_count = if maybe_buffers.is_some() {
+            self.sample_count
+        } else {
+            1
+        };
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let bind_group = BindGroup::new(&ctx.device, &controller.bindings());
+        let texture_bind_group = TextureBindGroup::new(ctx, &controller.textures());
+        let feedback = match controller.feedback_passes() {
+            0 => None,
+            count => Some(FeedbackBuffers::new(&ctx.device, count)),
+        };
+        let bind_group_layouts = [&bind_group.layout, &texture_bind_group.layout]
+            .into_iter()
+            .chain(feedback.as_ref().map(|feedback| &feedback.layout))
+            .collect::<Vec<_>>();
+        // Push constants must be set for exactly the stages of their range, so
+        // the compute pipeline gets its own layout, which also leaves out the
+        // feedback buffers
+        let create_pipeline_layout = |stages, bind_group_layouts: &[_]| {
+            ctx.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts,
+                    push_constant_ranges: &[wgpu::PushConstantRange {
+                        stages,
+                        range: 0..shared::push_constants::largest_size() as u32,
+                    }],
+                })
+        };
+        let render_pipeline_layout = create_pipeline_layout(
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            &bind_group_layouts,
+        );
+        let render_pipeline = create_pipeline(
             &self.options,
             &ctx.device,
-            &self.pipeline_layout,
-            ctx.config.format,
+            &render_pipeline_layout,
+            HDR_FORMAT,
             new_module,
+            shaders::main_fs,
             maybe_buffers.is_some(),
+            sample_count,
         );
+        let feedback_passes = feedback.as_ref().map_or(0, FeedbackBuffers::count);
+        let feedback_pipelines = (0..feedback_passes)
+            .map(|pass| {
+                create_pipeline(
+                    &self.options,
+                    &ctx.device,
+                    &render_pipeline_layout,
+                    feedback::FORMAT,
+                    new_module,
+                    feedback::entry_point(pass),
+                    false,
+                    1,
+                )
+            })
+            .collect();
+        let compute_pipeline = controller.dispatch().map(|_| {
+            ctx.device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: Some(&create_pipeline_layout(
+                        wgpu::ShaderStages::COMPUTE,
+                        &bind_group_layouts[..2],
+                    )),
+                    module: &create_shader_module(
+                        &self.options,
+                        &ctx.device,
+                        new_module.spv_module_for_entry_point(COMPUTE_ENTRY_POINT),
+                    ),
+                    entry_point: COMPUTE_ENTRY_POINT,
+                })
+        });
+        if let Some(err) = futures::executor::block_on(ctx.device.pop_error_scope()) {
+            return Err(err);
+        }
+        self.render_pipeline = Some(render_pipeline);
+        self.pipeline_sample_count = sample_count;
+        self.compute_pipeline = compute_pipeline;
+        self.feedback_pipelines = feedback_pipelines;
+        self.feedback = feedback;
+        self.buffers = maybe_create_buffers(ctx, maybe_buffers);
+        self.bind_group = Some(bind_group);
+        self.texture_bind_group = Some(texture_bind_group);
+        self.accumulated_frames = 0;
+        Ok(())
+    }
+
+    /// Whether the kinds of `controller`'s bindings or the number of its
+    /// textures differ from those the pipeline was created with, which needs
+    /// a [`RenderPass::new_module`].
+    pub fn layout_changed(&self, controller: &dyn Controller) -> bool {
+        self.bind_group
+            .as_ref()
+            .is_some_and(|bind_group| !bind_group.matches(&controller.bindings()))
+            || self
+                .texture_bind_group
+                .as_ref()
+                .is_some_and(|group| !group.matches(&controller.textures()))
+    }
+
+    /// Stops drawing until the next [`RenderPass::new_module`].
+    pub fn clear_pipeline(&mut self) {
+        self.render_pipeline = None;
+        self.pipeline_sample_count = 1;
+        self.compute_pipeline = None;
+        self.feedback_pipelines.clear();
+        self.feedback = None;
+        self.buffers = None;
+        self.bind_group = None;
+        self.texture_bind_group = None;
+        self.accumulated_frames = 0;
     }
 
     pub fn new_vertices(
@@ -244,6 +672,30 @@ impl RenderPass {
     ) {
         self.buffers = maybe_create_buffers(ctx, maybe_buffers);
     }
+
+    /// Uploads replaced images. A changed number of textures is left for
+    /// the pipeline rebuild after [`RenderPass::layout_changed`].
+    pub fn new_textures(&mut self, ctx: &GraphicsContext, textures: &[TextureBinding]) {
+        if let Some(texture_bind_group) = &mut self.texture_bind_group {
+            if texture_bind_group.matches(textures) {
+                texture_bind_group.update(ctx, textures);
+            }
+        }
+        self.accumulated_frames = 0;
+    }
+}
+
+/// Element `index` of the Halton sequence in `base`, which spreads evenly
+/// over [0, 1) however many elements are taken
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut weight = 1.0;
+    while index > 0 {
+        weight /= base as f32;
+        result += weight * (index % base) as f32;
+        index /= base;
+    }
+    result
 }
 
 fn maybe_create_buffers(
@@ -268,29 +720,36 @@ fn maybe_create_buffers(
     })
 }
 
+fn create_shader_module(
+    options: &Options,
+    device: &wgpu::Device,
+    module: wgpu::ShaderModuleDescriptorSpirV,
+) -> wgpu::ShaderModule {
+    // FIXME(eddyb) automate this decision by default.
+    if options.force_spirv_passthru {
+        unsafe { device.create_shader_module_spirv(&module) }
+    } else {
+        let wgpu::ShaderModuleDescriptorSpirV { label, source } = module;
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label,
+            source: wgpu::ShaderSource::SpirV(source),
+        })
+    }
+}
+
 fn create_pipeline(
     options: &Options,
     device: &wgpu::Device,
     pipeline_layout: &wgpu::PipelineLayout,
-    surface_format: wgpu::TextureFormat,
-    compiled_shader_modules: CompiledShaderModules,
+    target_format: wgpu::TextureFormat,
+    compiled_shader_modules: &CompiledShaderModules,
+    fs_entry_point: &str,
     has_buffers: bool,
+    sample_count: u32,
 ) -> wgpu::RenderPipeline {
-    // FIXME(eddyb) automate this decision by default.
-    let create_module = |module| {
-        if options.force_spirv_passthru {
-            unsafe { device.create_shader_module_spirv(&module) }
-        } else {
-            let wgpu::ShaderModuleDescriptorSpirV { label, source } = module;
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label,
-                source: wgpu::ShaderSource::SpirV(source),
-            })
-        }
-    };
+    let create_module = |module| create_shader_module(options, device, module);
 
     let vs_entry_point = shaders::main_vs;
-    let fs_entry_point = shaders::main_fs;
 
     let vs_module_descr = compiled_shader_modules.spv_module_for_entry_point(vs_entry_point);
     let fs_module_descr = compiled_shader_modules.spv_module_for_entry_point(fs_entry_point);
@@ -339,7 +798,7 @@ fn create_pipeline(
             None
         },
         multisample: wgpu::MultisampleState {
-            count: 1,
+            count: sample_count,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
@@ -347,7 +806,7 @@ fn create_pipeline(
             module: fs_module,
             entry_point: fs_entry_point,
             targets: &[Some(wgpu::ColorTargetState {
-                format: surface_format,
+                format: target_format,
                 blend: None,
                 write_mask: wgpu::ColorWrites::ALL,
             })],
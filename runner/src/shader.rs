@@ -1,5 +1,16 @@
-use crate::{Options, RustGPUShader};
+use crate::RustGPUShader;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{dep_info, spirv_cache, Options};
+#[cfg(not(target_arch = "wasm32"))]
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
 
 pub struct CompiledShaderModules {
     named_spv_modules: Vec<(Option<String>, wgpu::ShaderModuleDescriptorSpirV<'static>)>,
@@ -31,88 +42,250 @@ impl CompiledShaderModules {
     }
 }
 
-pub fn maybe_watch(
+/// The SPIR-V that `runner/builder` produced when the runner was built, as
+/// rust-gpu can't run in the browser. `None` for shaders it doesn't build.
+#[cfg(target_arch = "wasm32")]
+pub fn embedded(shader: RustGPUShader) -> Option<CompiledShaderModules> {
+    let module = match shader {
+        RustGPUShader::Mandelbrot => wgpu::include_spirv_raw!(env!("mandelbrot.spv")),
+        RustGPUShader::RayMarching => wgpu::include_spirv_raw!(env!("ray_marching.spv")),
+        RustGPUShader::RayMarching2D => wgpu::include_spirv_raw!(env!("ray_marching_2d.spv")),
+        RustGPUShader::SierpinskiTriangle => {
+            wgpu::include_spirv_raw!(env!("sierpinski_triangle.spv"))
+        }
+        RustGPUShader::KochSnowflake => wgpu::include_spirv_raw!(env!("koch_snowflake.spv")),
+        _ => return None,
+    };
+    Some(CompiledShaderModules {
+        named_spv_modules: vec![(None, module)],
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const TARGET: &str = "spirv-unknown-vulkan1.1";
+
+/// Also returns the source files the build read, see [`crate::dep_info`],
+/// if they are known.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compile(
     options: &Options,
-    #[cfg(not(target_arch = "wasm32"))] on_watch: Option<
-        Box<dyn FnMut(CompiledShaderModules) + Send + 'static>,
-    >,
-) -> CompiledShaderModules {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        use spirv_builder::{CompileResult, MetadataPrintout, SpirvBuilder};
-        use std::path::PathBuf;
-        // Hack: spirv_builder builds into a custom directory if running under cargo, to not
-        // deadlock, and the default target directory if not. However, packages like `proc-macro2`
-        // have different configurations when being built here vs. when building
-        // rustc_codegen_spirv normally, so we *want* to build into a separate target directory, to
-        // not have to rebuild half the crate graph every time we run. So, pretend we're running
-        // under cargo by setting these environment variables.
-        std::env::set_var("OUT_DIR", env!("OUT_DIR"));
-        std::env::set_var("PROFILE", env!("PROFILE"));
-        let crate_name = match options.shader {
-            RustGPUShader::Mandelbrot => "mandelbrot",
-            RustGPUShader::RayMarching => "ray-marching",
-            RustGPUShader::RayMarching2D => "ray-marching-2d",
-            RustGPUShader::SierpinskiTriangle => "sierpinski-triangle",
-            RustGPUShader::KochSnowflake => "koch-snowflake",
-            RustGPUShader::SDFs2D => "sdfs-2d",
-            RustGPUShader::SDFs3D => "sdfs-3d",
-            RustGPUShader::HydrogenWavefunction => "hydrogen-wavefunction",
-            RustGPUShader::SphericalHarmonics => "spherical-harmonics",
-            RustGPUShader::Gaussian => "gaussian",
-            RustGPUShader::SphericalHarmonicsShape => "spherical-harmonics-shape",
+    shader: RustGPUShader,
+) -> Result<(CompiledShaderModules, Option<Vec<PathBuf>>), BuildError> {
+    // Only single-module builds are cached; `--force-spirv-passthru` is for debugging anyway
+    let cached = !options.force_spirv_passthru;
+    if cached {
+        let sources = spirv_cache::sources(shader);
+        let key = spirv_cache::Key::new(shader, TARGET, &sources).ok();
+        if let Some(spirv) = key.and_then(spirv_cache::load) {
+            let modules = CompiledShaderModules {
+                named_spv_modules: vec![(None, spv_module(&spirv))],
+            };
+            return Ok((modules, Some(sources).filter(|sources| !sources.is_empty())));
+        }
+    }
+
+    let modules = build(options, shader)?;
+    let artifact = match &modules {
+        Modules::Single(path) => Some(path),
+        Modules::Multi(modules) => modules.values().next(),
+    };
+    let sources = artifact.and_then(|artifact| {
+        dep_info::sources(artifact, &dep_info::crate_name(&crate_path(shader)))
+    });
+    // Keyed by what this build read, which the next launch finds in the
+    // stored sources
+    let cache_key = cached
+        .then(|| {
+            let sources = sources.as_deref().unwrap_or_default();
+            if let Err(err) = spirv_cache::store_sources(shader, sources) {
+                eprintln!("Failed to cache the sources of {shader}: {err}");
+            }
+            spirv_cache::Key::new(shader, TARGET, sources).ok()
+        })
+        .flatten();
+    let load_spv_module = |path: PathBuf| {
+        let data = std::fs::read(&path).map_err(|err| BuildError {
+            message: format!("Failed to read {}: {err}", path.display()),
+            output: String::new(),
+        })?;
+        if let Some(key) = cache_key {
+            if let Err(err) = spirv_cache::store(key, &data) {
+                eprintln!("Failed to cache SPIR-V for {shader}: {err}");
+            }
+        }
+        Ok(spv_module(&data))
+    };
+    let modules = CompiledShaderModules {
+        named_spv_modules: match modules {
+            Modules::Single(path) => vec![(None, load_spv_module(path)?)],
+            Modules::Multi(modules) => modules
+                .into_iter()
+                .map(|(name, path)| Ok((Some(name), load_spv_module(path)?)))
+                .collect::<Result<_, BuildError>>()?,
+        },
+    };
+    Ok((modules, sources))
+}
+
+/// A failed build of a shader crate
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BuildError {
+    pub message: String,
+    /// Everything rust-gpu printed, including rustc's diagnostics
+    pub output: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Where a build put the SPIR-V, passed from [`build_in_child`] to [`build`]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Serialize, Deserialize)]
+enum Modules {
+    Single(PathBuf),
+    /// Indexed by entry point
+    Multi(BTreeMap<String, PathBuf>),
+}
+
+/// Runs rust-gpu in a child process, as spirv-builder prints rustc's output
+/// straight to stderr, which only a parent process can capture. The output
+/// still reaches the terminal as it arrives.
+#[cfg(not(target_arch = "wasm32"))]
+fn build(options: &Options, shader: RustGPUShader) -> Result<Modules, BuildError> {
+    let error = |message: String, output: &str| BuildError {
+        message,
+        output: output.to_string(),
+    };
+    let exe = std::env::current_exe()
+        .map_err(|err| error(format!("Failed to find the runner executable: {err}"), ""))?;
+    let mut command = Command::new(exe);
+    command.arg("--build-shader").arg(shader.to_string());
+    if options.force_spirv_passthru {
+        command.arg("--force-spirv-passthru");
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| error(format!("Failed to start rust-gpu: {err}"), ""))?;
+    let (Some(stderr), Some(mut stdout)) = (child.stderr.take(), child.stdout.take()) else {
+        return Err(error("Failed to capture rust-gpu's output".to_string(), ""));
+    };
+
+    let mut output = String::new();
+    for line in BufReader::new(stderr).lines() {
+        let Ok(line) = line else {
+            break;
         };
-        let manifest_dir = env!("CARGO_MANIFEST_DIR");
-        let crate_path = [manifest_dir, "..", "shaders", crate_name]
-            .iter()
-            .copied()
-            .collect::<PathBuf>();
-
-        let has_debug_printf = options.force_spirv_passthru;
-
-        let builder = SpirvBuilder::new(crate_path, "spirv-unknown-vulkan1.1")
-            .print_metadata(MetadataPrintout::None)
-            .shader_panic_strategy(if has_debug_printf {
-                spirv_builder::ShaderPanicStrategy::DebugPrintfThenExit {
-                    print_inputs: true,
-                    print_backtrace: true,
-                }
-            } else {
-                spirv_builder::ShaderPanicStrategy::SilentExit
-            })
-            // HACK(eddyb) needed because of `debugPrintf` instrumentation limitations
-            // (see https://github.com/KhronosGroup/SPIRV-Tools/issues/4892).
-            .multimodule(has_debug_printf);
-        let initial_result = if let Some(mut f) = on_watch {
-            builder
-                .watch(move |compile_result| f(handle_compile_result(compile_result)))
-                .expect("Configuration is correct for watching")
+        eprintln!("{line}");
+        output.push_str(&line);
+        output.push('\n');
+    }
+    let mut modules = String::new();
+    stdout
+        .read_to_string(&mut modules)
+        .map_err(|err| error(format!("Failed to read rust-gpu's output: {err}"), &output))?;
+    let status = child
+        .wait()
+        .map_err(|err| error(format!("Failed to wait for rust-gpu: {err}"), &output))?;
+    if !status.success() {
+        let message = output
+            .lines()
+            .last()
+            .unwrap_or("rust-gpu failed")
+            .to_string();
+        return Err(error(message, &output));
+    }
+    modules
+        .lines()
+        .last()
+        .and_then(|line| serde_json::from_str(line).ok())
+        .ok_or_else(|| error("rust-gpu printed no modules".to_string(), &output))
+}
+
+/// The child process side of [`build`], run by `--build-shader`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_in_child(options: &Options, shader: RustGPUShader) {
+    use spirv_builder::{MetadataPrintout, ModuleResult, SpirvBuilder};
+    // Hack: spirv_builder builds into a custom directory if running under cargo, to not
+    // deadlock, and the default target directory if not. However, packages like `proc-macro2`
+    // have different configurations when being built here vs. when building
+    // rustc_codegen_spirv normally, so we *want* to build into a separate target directory, to
+    // not have to rebuild half the crate graph every time we run. So, pretend we're running
+    // under cargo by setting these environment variables.
+    std::env::set_var("OUT_DIR", env!("OUT_DIR"));
+    std::env::set_var("PROFILE", env!("PROFILE"));
+
+    let has_debug_printf = options.force_spirv_passthru;
+    let builder = SpirvBuilder::new(crate_path(shader), TARGET)
+        .print_metadata(MetadataPrintout::None)
+        .shader_panic_strategy(if has_debug_printf {
+            spirv_builder::ShaderPanicStrategy::DebugPrintfThenExit {
+                print_inputs: true,
+                print_backtrace: true,
+            }
         } else {
-            builder.build().unwrap()
-        };
-        fn handle_compile_result(compile_result: CompileResult) -> CompiledShaderModules {
-            let load_spv_module = |path| {
-                let data = std::fs::read(path).unwrap();
-                // FIXME(eddyb) this reallocates all the data pointlessly, there is
-                // not a good reason to use `ShaderModuleDescriptorSpirV` specifically.
-                let spirv = Cow::Owned(wgpu::util::make_spirv_raw(&data).into_owned());
-                wgpu::ShaderModuleDescriptorSpirV {
-                    label: None,
-                    source: spirv,
-                }
+            spirv_builder::ShaderPanicStrategy::SilentExit
+        })
+        // HACK(eddyb) needed because of `debugPrintf` instrumentation limitations
+        // (see https://github.com/KhronosGroup/SPIRV-Tools/issues/4892).
+        .multimodule(has_debug_printf);
+    match builder.build() {
+        Ok(result) => {
+            let modules = match result.module {
+                ModuleResult::SingleModule(path) => Modules::Single(path),
+                ModuleResult::MultiModule(modules) => Modules::Multi(modules),
             };
-            CompiledShaderModules {
-                named_spv_modules: match compile_result.module {
-                    spirv_builder::ModuleResult::SingleModule(path) => {
-                        vec![(None, load_spv_module(path))]
-                    }
-                    spirv_builder::ModuleResult::MultiModule(modules) => modules
-                        .into_iter()
-                        .map(|(name, path)| (Some(name), load_spv_module(path)))
-                        .collect(),
-                },
-            }
+            println!("{}", serde_json::to_string(&modules).unwrap());
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
         }
-        handle_compile_result(initial_result)
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spv_module(data: &[u8]) -> wgpu::ShaderModuleDescriptorSpirV<'static> {
+    // FIXME(eddyb) this reallocates all the data pointlessly, there is
+    // not a good reason to use `ShaderModuleDescriptorSpirV` specifically.
+    let spirv = Cow::Owned(wgpu::util::make_spirv_raw(data).into_owned());
+    wgpu::ShaderModuleDescriptorSpirV {
+        label: None,
+        source: spirv,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn crate_path(shader: RustGPUShader) -> PathBuf {
+    let crate_name = match shader {
+        RustGPUShader::Mandelbrot => "mandelbrot",
+        RustGPUShader::RayMarching => "ray-marching",
+        RustGPUShader::RayMarching2D => "ray-marching-2d",
+        RustGPUShader::SierpinskiTriangle => "sierpinski-triangle",
+        RustGPUShader::KochSnowflake => "koch-snowflake",
+        RustGPUShader::SDFs2D => "sdfs-2d",
+        RustGPUShader::SDFs3D => "sdfs-3d",
+        RustGPUShader::HydrogenWavefunction => "hydrogen-wavefunction",
+        RustGPUShader::SphericalHarmonics => "spherical-harmonics",
+        RustGPUShader::Gaussian => "gaussian",
+        RustGPUShader::SphericalHarmonicsShape => "spherical-harmonics-shape",
+        RustGPUShader::Voronoi => "voronoi",
+        RustGPUShader::Lens => "lens",
+        RustGPUShader::GameOfLife => "game-of-life",
+        RustGPUShader::Trails => "trails",
+    };
+    shaders_path().join(crate_name)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shaders_path() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "..", "shaders"]
+        .iter()
+        .copied()
+        .collect()
+}
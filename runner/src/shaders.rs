@@ -9,3 +9,7 @@ pub mod sierpinski_triangle;
 pub mod spherical_harmonics;
 pub mod gaussian;
 pub mod spherical_harmonics_shape;
+pub mod voronoi;
+pub mod lens;
+pub mod game_of_life;
+pub mod trails;
@@ -0,0 +1,204 @@
+use crate::{
+    bindings::Binding,
+    controller::ParamsUi,
+    presets::{self, Preset},
+    window::UserEvent,
+};
+use bytemuck::Zeroable;
+use egui::Context;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use shared::push_constants::game_of_life::{ShaderConstants, CELL_SIZE};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, MouseButton},
+    event_loop::EventLoopProxy,
+};
+
+/// Matches `threads` of `main_cs`
+const WORKGROUP_SIZE: u32 = 8;
+
+pub struct Controller {
+    size: PhysicalSize<u32>,
+    grid: PhysicalSize<u32>,
+    /// Random cells that the buffers start with, which the compute shader
+    /// takes over from
+    seed: Vec<u32>,
+    cursor: Vec2,
+    drawing: bool,
+    step: u32,
+    /// Index of the buffer holding the current cells, swapped by each
+    /// dispatch that takes a step
+    parity: u32,
+    shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    #[param(range = 1.0..=60.0, speed = 0.1)]
+    steps_per_second: f32,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if !self.steps_per_second.is_finite() || self.steps_per_second <= 0.0 {
+            return Err(format!(
+                "steps_per_second must be positive, got {}",
+                self.steps_per_second
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// About a quarter of the cells alive, from a xorshift generator so that
+/// every size starts the same way
+fn random_cells(count: usize) -> Vec<u32> {
+    let mut x = 0x9e37_79b9_u32;
+    (0..count)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            (x & 3 == 0) as u32
+        })
+        .collect()
+}
+
+impl crate::controller::Controller for Controller {
+    fn new(size: PhysicalSize<u32>) -> Self {
+        let mut controller = Self {
+            size,
+            grid: PhysicalSize::new(0, 0),
+            seed: vec![],
+            cursor: Vec2::ZERO,
+            drawing: false,
+            step: 0,
+            parity: 0,
+            shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                steps_per_second: 15.0,
+            },
+        };
+        controller.resize(size);
+        controller
+    }
+
+    fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        if button == MouseButton::Left {
+            self.drawing = state == ElementState::Pressed;
+        }
+    }
+
+    fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor = vec2(position.x as f32, position.y as f32) / CELL_SIZE as f32;
+    }
+
+    fn focus_lost(&mut self) {
+        self.drawing = false;
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+        let grid = PhysicalSize::new(
+            size.width.div_ceil(CELL_SIZE),
+            size.height.div_ceil(CELL_SIZE),
+        );
+        if grid != self.grid {
+            // The buffers change size, so the board starts over
+            self.grid = grid;
+            self.seed = random_cells((grid.width * grid.height) as usize);
+        }
+    }
+
+    fn update(&mut self, time: f32) {
+        let step = (time * self.state.steps_per_second) as u32;
+        // Paused, or between steps, the cells only change by drawing. Frames
+        // that span several steps, or seeking, take a single step.
+        let advance = step != self.step;
+        self.shader_constants = ShaderConstants {
+            size: self.size.into(),
+            grid: self.grid.into(),
+            cursor: self.cursor.into(),
+            drawing: self.drawing as u32,
+            advance: advance as u32,
+            parity: self.parity ^ advance as u32,
+        };
+        self.step = step;
+    }
+
+    fn push_constants(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.shader_constants)
+    }
+
+    fn bindings(&self) -> Vec<Binding<'_>> {
+        // Each step reads one buffer and writes the other
+        vec![
+            Binding::writable_storage(&self.seed),
+            Binding::writable_storage(&self.seed),
+        ]
+    }
+
+    fn dispatch(&self) -> Option<[u32; 3]> {
+        Some([
+            self.grid.width.div_ceil(WORKGROUP_SIZE),
+            self.grid.height.div_ceil(WORKGROUP_SIZE),
+            1,
+        ])
+    }
+
+    fn dispatched(&mut self) {
+        self.parity = self.shader_constants.parity;
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
+    }
+
+    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
+        self.state.params_ui(ui);
+        ui.label("Draw cells with the left mouse button");
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::controller::Controller as _;
+
+    fn parity(controller: &Controller) -> u32 {
+        controller.shader_constants.parity
+    }
+
+    #[test]
+    fn test_parity_follows_dispatches() {
+        let mut controller = Controller::new(PhysicalSize::new(64, 64));
+        // 15 steps per second, so this skips several steps in one frame
+        controller.update(1.0);
+        assert_eq!(controller.shader_constants.advance, 1);
+        assert_eq!(parity(&controller), 1);
+        controller.dispatched();
+
+        // Without a dispatch the step is not taken, and the buffers stay put
+        controller.update(2.0);
+        assert_eq!(parity(&controller), 0);
+        controller.update(2.0);
+        assert_eq!(controller.shader_constants.advance, 0);
+        assert_eq!(parity(&controller), 1);
+
+        // Seeking backwards steps forwards once
+        controller.update(0.5);
+        assert_eq!(controller.shader_constants.advance, 1);
+        assert_eq!(parity(&controller), 0);
+    }
+}
@@ -1,46 +1,58 @@
+use crate::{
+    presets::{self, Preset},
+    window::UserEvent,
+};
 use bytemuck::Zeroable;
-use egui::{vec2, Color32, Context, Event, Rect, RichText, Sense, Stroke, Ui, Vec2};
-use glam::Quat;
+use egui::{Color32, Context, Rect, RichText, Sense, Stroke, Ui};
+use glam::{vec2, Quat, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::spherical_harmonics::ShaderConstants;
-use std::time::Instant;
 use winit::event::{ElementState, MouseScrollDelta};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::MouseButton,
+    event_loop::EventLoopProxy,
 };
 
 use core::f32::consts::PI;
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
-    q: Quat,
-    zoom: f32,
     mouse_button_pressed: bool,
     shader_constants: ShaderConstants,
+    negative_m: bool,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    q: Quat,
+    zoom: f32,
     l: u32,
     m: i32,
-    negative_m: bool,
 }
 
+impl Preset for State {}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            q: Quat::from_xyzw(-0.004286735, -0.18652226, -0.000813862, 0.98244107),
-            zoom: 1.0,
             mouse_button_pressed: false,
             shader_constants: ShaderConstants::zeroed(),
-            l: 2,
-            m: 1,
             negative_m: false,
+            state: State {
+                q: Quat::from_xyzw(-0.004286735, -0.18652226, -0.000813862, 0.98244107),
+                zoom: 1.0,
+                l: 2,
+                m: 1,
+            },
         }
     }
 
@@ -50,7 +62,8 @@ impl crate::controller::Controller for Controller {
                 ElementState::Pressed => true,
                 ElementState::Released => {
                     let angles = PI * (self.drag_start - self.drag_end) / self.size.height as f32;
-                    self.q = self
+                    self.state.q = self
+                        .state
                         .q
                         .mul_quat(Quat::from_rotation_y(-angles.x))
                         .mul_quat(Quat::from_rotation_x(angles.y))
@@ -90,7 +103,7 @@ impl crate::controller::Controller for Controller {
                 }
             }
         };
-        self.zoom *= scroll;
+        self.state.zoom *= scroll;
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -98,26 +111,23 @@ impl crate::controller::Controller for Controller {
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, time: f32) {
         let angles = PI * (self.drag_start - self.drag_end) / self.size.height as f32;
         let q = self
+            .state
             .q
             .mul_quat(Quat::from_rotation_y(-angles.x))
             .mul_quat(Quat::from_rotation_x(angles.y));
         self.shader_constants = ShaderConstants {
-            width: self.size.width,
-            height: self.size.height,
-            time: self.start.elapsed().as_secs_f32(),
-            cursor_x: self.cursor.x,
-            cursor_y: self.cursor.y,
-            zoom: self.zoom,
+            size: self.size.into(),
+            time,
+            cursor: self.cursor.into(),
+            zoom: self.state.zoom,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
-            l: self.l,
-            m: self.m,
-            x: q.x,
-            y: q.y,
-            z: q.z,
-            w: q.w,
+            l: self.state.l,
+            m: self.state.m,
+            quat: q.into(),
+            variant: 0,
         };
     }
 
@@ -125,24 +135,34 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.negative_m = self.state.m < 0;
+        Ok(())
+    }
+
+    fn has_ui(&mut self) -> bool {
         true
     }
 
-    fn ui(&mut self, ctx: &Context, ui: &mut Ui) {
+    fn ui(&mut self, ctx: &Context, ui: &mut Ui, _: &EventLoopProxy<UserEvent>) {
         let (rect, response) = ui.allocate_at_least([220.0; 2].into(), Sense::drag());
         let l_max = 9;
 
         if let Some(mouse_pos) = response.interact_pointer_pos() {
             let v = ((mouse_pos - rect.left_top()) * (l_max + 1) as f32 / rect.width())
-                .clamp(Vec2::ZERO, Vec2::splat(l_max as f32));
+                .clamp(egui::Vec2::ZERO, egui::Vec2::splat(l_max as f32));
             if v.x > v.y {
                 let dif = v.x - v.y;
-                self.l = (v.y + (dif / 2.0)) as u32;
-                self.m = (v.x - (dif / 2.0)) as i32;
+                self.state.l = (v.y + (dif / 2.0)) as u32;
+                self.state.m = (v.x - (dif / 2.0)) as i32;
             } else {
-                self.l = v.y as u32;
-                self.m = v.x as i32;
+                self.state.l = v.y as u32;
+                self.state.m = v.x as i32;
             }
             ctx.input(|input| {
                 if input.pointer.any_pressed() {
@@ -150,7 +170,7 @@ impl crate::controller::Controller for Controller {
                 }
             });
             if self.negative_m {
-                self.m = -self.m;
+                self.state.m = -self.state.m;
             }
         }
 
@@ -158,15 +178,15 @@ impl crate::controller::Controller for Controller {
         for l in 0..=l_max {
             for m in 0..=l as i32 {
                 let circle_pos = rect.left_top()
-                    + vec2(m as f32, l as f32)
+                    + egui::vec2(m as f32, l as f32)
                         * ((rect.width() - circle_radius * 2.0) / l_max as f32)
-                    + Vec2::splat(circle_radius);
+                    + egui::Vec2::splat(circle_radius);
                 ui.painter().circle(
                     circle_pos,
                     circle_radius,
-                    if l == self.l && m == self.m {
+                    if l == self.state.l && m == self.state.m {
                         Color32::DARK_GREEN
-                    } else if l == self.l && m == -self.m {
+                    } else if l == self.state.l && m == -self.state.m {
                         Color32::from_rgb(0, 0x64, 0x64)
                     } else {
                         Color32::DARK_GRAY
@@ -177,15 +197,15 @@ impl crate::controller::Controller for Controller {
         }
 
         ui.put(
-            Rect::from_min_max(rect.min + vec2(rect.width() - 150.0, 4.0), rect.max),
+            Rect::from_min_max(rect.min + egui::vec2(rect.width() - 150.0, 4.0), rect.max),
             |ui: &mut Ui| {
                 ui.horizontal_wrapped(|ui| {
                     let text_size = 36.0;
                     ui.spacing_mut().item_spacing *= 0.0;
                     ui.heading(RichText::new("Y").size(text_size));
                     ui.vertical(|ui| {
-                        ui.label(RichText::new(format!(" {}", self.m)).size(text_size / 2.0));
-                        ui.label(RichText::new(format!("{}", self.l)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!(" {}", self.state.m)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!("{}", self.state.l)).size(text_size / 2.0));
                     });
                     ui.heading(RichText::new("(θ, φ)").size(text_size))
                 })
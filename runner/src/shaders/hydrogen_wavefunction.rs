@@ -1,46 +1,75 @@
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    presets::{self, Preset},
+};
 use bytemuck::Zeroable;
-use egui::{vec2, Context, Vec2};
+use egui::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::hydrogen_wavefunction::ShaderConstants;
-use std::time::Instant;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, MouseButton, MouseScrollDelta},
-    event_loop::EventLoopProxy,
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
-    camera: Vec2,
-    camera_distance: f32,
     mouse_button_pressed: bool,
+    state: State,
     shader_constants: ShaderConstants,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    camera: Vec2,
+    camera_distance: f32,
+    #[param(label = "root", range = 1..=6, speed = 0.1)]
+    root: i32,
+    #[param(label = "n", range = 1..=5, speed = 0.1)]
     n: i32,
+    #[param(label = "l", range = 0..=self.n - 1, speed = 0.1)]
     l: i32,
+    #[param(label = "m", range = -self.l..=self.l, speed = 0.1)]
     m: i32,
-    root: i32,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=6).contains(&self.root) {
+            return Err(format!("root must be from 1 to 6, got {}", self.root));
+        }
+        if !(1..=5).contains(&self.n) {
+            return Err(format!("n must be from 1 to 5, got {}", self.n));
+        }
+        if !(0..self.n).contains(&self.l) {
+            return Err(format!("l must be from 0 to n - 1, got {}", self.l));
+        }
+        if self.m.abs() > self.l {
+            return Err(format!("m must be from -l to l, got {}", self.m));
+        }
+        Ok(())
+    }
 }
 
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            camera: Vec2::ZERO,
-            camera_distance: 30.0,
             mouse_button_pressed: false,
+            state: State {
+                camera: Vec2::ZERO,
+                camera_distance: 30.0,
+                root: 2,
+                n: 1,
+                l: 0,
+                m: 0,
+            },
             shader_constants: ShaderConstants::zeroed(),
-            n: 1,
-            l: 0,
-            m: 0,
-            root: 2,
         }
     }
 
@@ -49,7 +78,7 @@ impl crate::controller::Controller for Controller {
             self.mouse_button_pressed = match state {
                 ElementState::Pressed => true,
                 ElementState::Released => {
-                    self.camera += self.drag_start - self.drag_end;
+                    self.state.camera += self.drag_start - self.drag_end;
                     false
                 }
             };
@@ -85,8 +114,8 @@ impl crate::controller::Controller for Controller {
                 }
             }
         };
-        self.camera_distance *= scroll;
-        // self.camera *= 1.0 / scroll;
+        self.state.camera_distance *= scroll;
+        // self.state.camera *= 1.0 / scroll;
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -94,65 +123,53 @@ impl crate::controller::Controller for Controller {
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, time: f32) {
         self.shader_constants = ShaderConstants {
             width: self.size.width,
             height: self.size.height,
-            time: self.start.elapsed().as_secs_f32(),
+            time,
             cursor_x: self.cursor.x,
             cursor_y: self.cursor.y,
-            camera_distance: self.camera_distance,
-            translate_x: self.camera.x + self.drag_start.x - self.drag_end.x,
-            translate_y: self.camera.y + self.drag_start.y - self.drag_end.y,
+            camera_distance: self.state.camera_distance,
+            translate_x: self.state.camera.x + self.drag_start.x - self.drag_end.x,
+            translate_y: self.state.camera.y + self.drag_start.y - self.drag_end.y,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
-            n: self.n as u32,
-            l: self.l as u32,
-            m: self.m,
-            root: self.root,
+            n: self.state.n as u32,
+            l: self.state.l as u32,
+            m: self.state.m,
+            root: self.state.root,
+            jitter: self.shader_constants.jitter,
         };
-        println!("{}", self.camera_distance);
+        println!("{}", self.state.camera_distance);
     }
 
     fn push_constants(&self) -> &[u8] {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn accumulation_key(&self) -> Vec<u8> {
+        // The shader doesn't animate, so the frames add up while time passes
+        let constants = ShaderConstants {
+            time: 0.0,
+            ..self.shader_constants
+        };
+        bytemuck::bytes_of(&constants).to_vec()
+    }
+
+    fn set_jitter(&mut self, jitter: glam::Vec2) {
+        self.shader_constants.jitter = jitter.into();
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
     }
 
-    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
-        ui.horizontal(|ui| {
-            ui.label("root:");
-            ui.add(
-                egui::DragValue::new(&mut self.root)
-                    .clamp_range(1..=6)
-                    .speed(0.1),
-            );
-        });
-        ui.horizontal(|ui| {
-            ui.label("n:");
-            ui.add(
-                egui::DragValue::new(&mut self.n)
-                    .clamp_range(1..=5)
-                    .speed(0.1),
-            );
-        });
-        ui.horizontal(|ui| {
-            ui.label("l:");
-            ui.add(
-                egui::DragValue::new(&mut self.l)
-                    .clamp_range(0..=self.n - 1)
-                    .speed(0.1),
-            );
-        });
-        ui.horizontal(|ui| {
-            ui.label("m:");
-            ui.add(
-                egui::DragValue::new(&mut self.m)
-                    .clamp_range(-self.l..=self.l)
-                    .speed(0.1),
-            );
-        });
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 }
@@ -1,17 +1,45 @@
-use crate::window::UserEvent;
+use crate::{
+    navigation::{key_navigation, KeyNavigation},
+    presets::{self, Preset},
+    window::UserEvent,
+};
 use bytemuck::Zeroable;
 use egui::{vec2, Context, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::koch_snowflake::ShaderConstants;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
+    event::KeyboardInput,
     event_loop::EventLoopProxy,
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
     cursor: Vec2,
-    use_antisnowflake: bool,
     shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    use_antisnowflake: bool,
+    #[serde(default = "default_zoom")]
+    zoom: f32,
+    #[serde(default)]
+    translate: glam::Vec2,
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if !self.zoom.is_finite() || self.zoom <= 0.0 {
+            return Err(format!("zoom must be positive, got {}", self.zoom));
+        }
+        Ok(())
+    }
 }
 
 impl crate::controller::Controller for Controller {
@@ -19,8 +47,12 @@ impl crate::controller::Controller for Controller {
         Self {
             size,
             cursor: Vec2::ZERO,
-            use_antisnowflake: false,
             shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                use_antisnowflake: false,
+                zoom: default_zoom(),
+                translate: glam::Vec2::ZERO,
+            },
         }
     }
 
@@ -28,18 +60,38 @@ impl crate::controller::Controller for Controller {
         self.cursor = vec2(position.x as f32, position.y as f32);
     }
 
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        match key_navigation(input) {
+            Some(KeyNavigation::Pan(offset)) => self.pan(-offset),
+            Some(KeyNavigation::Zoom(lines)) => self.pinch(1.1_f32.powf(lines)),
+            None => {}
+        }
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        self.state.zoom /= scale;
+    }
+
+    fn pan(&mut self, delta: glam::Vec2) {
+        self.state.translate -=
+            glam::vec2(delta.x, -delta.y) / self.size.height as f32 * self.state.zoom;
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size.width = size.width;
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, _time: f32) {
         self.shader_constants = ShaderConstants {
             width: self.size.width,
             height: self.size.height,
             cursor_x: self.cursor.x,
             cursor_y: self.cursor.y,
-            use_antisnowflake: self.use_antisnowflake as u32,
+            use_antisnowflake: self.state.use_antisnowflake as u32,
+            zoom: self.state.zoom,
+            translate_x: self.state.translate.x,
+            translate_y: self.state.translate.y,
         };
     }
 
@@ -47,12 +99,21 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
+
+    fn has_ui(&mut self) -> bool {
         true
     }
 
     fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
-        ui.radio_value(&mut self.use_antisnowflake, false, "Snowflake");
-        ui.radio_value(&mut self.use_antisnowflake, true, "AntiSnowflake");
+        ui.radio_value(&mut self.state.use_antisnowflake, false, "Snowflake");
+        ui.radio_value(&mut self.state.use_antisnowflake, true, "AntiSnowflake");
     }
 }
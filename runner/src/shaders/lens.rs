@@ -0,0 +1,149 @@
+use crate::{
+    bindings::TextureBinding,
+    controller::ParamsUi,
+    presets::{self, Preset},
+    window::UserEvent,
+};
+use bytemuck::Zeroable;
+use egui::Context;
+use serde::{Deserialize, Serialize};
+use shared::push_constants::lens::ShaderConstants;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::MouseScrollDelta,
+    event_loop::EventLoopProxy,
+};
+
+pub struct Controller {
+    size: PhysicalSize<u32>,
+    cursor: glam::Vec2,
+    image: image::RgbaImage,
+    /// Path typed into the ui, and why it couldn't be loaded
+    path: String,
+    load_error: Option<String>,
+    shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    #[param(range = 0.05..=0.5, speed = 0.002)]
+    radius: f32,
+    #[param(range = 1.0..=8.0, speed = 0.02)]
+    magnification: f32,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.radius > 0.0 && self.magnification >= 1.0) {
+            return Err(format!(
+                "radius must be positive and magnification at least 1, got {} and {}",
+                self.radius, self.magnification
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl crate::controller::Controller for Controller {
+    fn new(size: PhysicalSize<u32>) -> Self {
+        // Bundled, so that it is also there on the web
+        let image = image::load_from_memory(include_bytes!("../../assets/grid.png"))
+            .unwrap()
+            .into_rgba8();
+        Self {
+            size,
+            cursor: glam::vec2(size.width as f32, size.height as f32) / 2.0,
+            image,
+            path: String::new(),
+            load_error: None,
+            shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                radius: 0.2,
+                magnification: 3.0,
+            },
+        }
+    }
+
+    fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor = glam::vec2(position.x as f32, position.y as f32);
+    }
+
+    fn mouse_scroll(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(p) => (1.0 + p.y.abs() as f32).ln() * p.y.signum() as f32,
+        };
+        self.pinch(1.1_f32.powf(lines));
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        self.state.magnification = (self.state.magnification * scale).clamp(1.0, 8.0);
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    fn update(&mut self, _time: f32) {
+        let (width, height) = self.image.dimensions();
+        self.shader_constants = ShaderConstants {
+            size: self.size.into(),
+            image_size: PhysicalSize::new(width, height).into(),
+            cursor: self.cursor.into(),
+            radius: self.state.radius,
+            magnification: self.state.magnification,
+        };
+    }
+
+    fn push_constants(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.shader_constants)
+    }
+
+    fn textures(&self) -> Vec<TextureBinding<'_>> {
+        vec![TextureBinding::new(&self.image)]
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
+    }
+
+    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, event_proxy: &EventLoopProxy<UserEvent>) {
+        self.state.params_ui(ui);
+        // There is no file system to load from on the web
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            let response =
+                ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("Path to an image"));
+            let submitted =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Load").clicked() || submitted {
+                match crate::bindings::load_image(self.path.as_ref()) {
+                    Ok(image) => {
+                        self.image = image;
+                        self.load_error = None;
+                        if event_proxy.send_event(UserEvent::NewTexturesReady).is_err() {
+                            panic!("Event loop dead");
+                        }
+                    }
+                    Err(err) => self.load_error = Some(err),
+                }
+            }
+        });
+        if let Some(err) = &self.load_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
+}
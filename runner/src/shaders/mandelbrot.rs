@@ -1,41 +1,53 @@
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    navigation::{key_navigation, KeyNavigation},
+    presets::{self, Preset},
+};
 use bytemuck::Zeroable;
-use egui::{vec2, Context, Vec2};
+use egui::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::mandelbrot::ShaderConstants;
-use std::time::Instant;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta},
-    event_loop::EventLoopProxy,
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta},
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
+    mouse_button_pressed: bool,
+    state: State,
+    shader_constants: ShaderConstants,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
     camera: Vec2,
     zoom: f32,
-    mouse_button_pressed: bool,
+    #[param(range = 1.0..=6.0, speed = 0.1)]
     exponent: f32,
+    #[param(range = 2..=200, speed = 1)]
     num_iterations: u32,
-    shader_constants: ShaderConstants,
 }
 
+impl Preset for State {}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            camera: Vec2::ZERO,
-            zoom: 1.0,
             mouse_button_pressed: false,
-            exponent: 2.0,
-            num_iterations: 35,
+            state: State {
+                camera: Vec2::ZERO,
+                zoom: 1.0,
+                exponent: 2.0,
+                num_iterations: 35,
+            },
             shader_constants: ShaderConstants::zeroed(),
         }
     }
@@ -45,7 +57,7 @@ impl crate::controller::Controller for Controller {
             self.mouse_button_pressed = match state {
                 ElementState::Pressed => true,
                 ElementState::Released => {
-                    self.camera += self.drag_start - self.drag_end;
+                    self.state.camera += self.drag_start - self.drag_end;
                     false
                 }
             };
@@ -81,8 +93,27 @@ impl crate::controller::Controller for Controller {
                 }
             }
         };
-        self.zoom *= scroll;
-        self.camera *= 1.0 / scroll;
+        self.state.zoom *= scroll;
+        self.state.camera *= 1.0 / scroll;
+    }
+
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        match key_navigation(input) {
+            Some(KeyNavigation::Pan(offset)) => self.pan(-offset),
+            Some(KeyNavigation::Zoom(lines)) => {
+                self.mouse_scroll(MouseScrollDelta::LineDelta(0.0, lines))
+            }
+            None => {}
+        }
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        self.state.zoom /= scale;
+        self.state.camera *= scale;
+    }
+
+    fn pan(&mut self, delta: glam::Vec2) {
+        self.state.camera -= vec2(delta.x, delta.y);
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -90,19 +121,19 @@ impl crate::controller::Controller for Controller {
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, time: f32) {
         self.shader_constants = ShaderConstants {
             width: self.size.width,
             height: self.size.height,
-            time: self.start.elapsed().as_secs_f32(),
+            time,
             cursor_x: self.cursor.x,
             cursor_y: self.cursor.y,
-            zoom: self.zoom,
-            translate_x: self.camera.x + self.drag_start.x - self.drag_end.x,
-            translate_y: self.camera.y + self.drag_start.y - self.drag_end.y,
+            zoom: self.state.zoom,
+            translate_x: self.state.camera.x + self.drag_start.x - self.drag_end.x,
+            translate_y: self.state.camera.y + self.drag_start.y - self.drag_end.y,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
-            exponent: self.exponent,
-            num_iterations: self.num_iterations,
+            exponent: self.state.exponent,
+            num_iterations: self.state.num_iterations,
         };
     }
 
@@ -110,26 +141,25 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn accumulation_key(&self) -> Vec<u8> {
+        // The shader doesn't animate, so the frames add up while time passes
+        let constants = ShaderConstants {
+            time: 0.0,
+            ..self.shader_constants
+        };
+        bytemuck::bytes_of(&constants).to_vec()
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
     }
 
-    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
-        ui.horizontal(|ui| {
-            ui.label("Exponent:");
-            ui.add(
-                egui::DragValue::new(&mut self.exponent)
-                    .clamp_range(1.0..=6.0)
-                    .speed(0.1),
-            );
-        });
-        ui.horizontal(|ui| {
-            ui.label("Num Iterations:");
-            ui.add(
-                egui::DragValue::new(&mut self.num_iterations)
-                    .clamp_range(2..=200)
-                    .speed(1),
-            );
-        });
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 }
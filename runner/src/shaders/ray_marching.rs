@@ -1,46 +1,62 @@
+use crate::{
+    navigation::FlyCamera,
+    presets::{self, Preset},
+};
 use bytemuck::Zeroable;
 use egui::{vec2, Vec2};
+use glam::{Mat3, Vec3};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::ray_marching::ShaderConstants;
-use std::time::{Duration, Instant};
+use std::f32::consts::PI;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta},
+    event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta},
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
-    elapsed: Duration,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
-    camera: Vec2,
-    zoom: f32,
     mouse_button_pressed: bool,
+    fly_camera: FlyCamera,
 
     scroll: f32,
     drag: Vec2,
     prev_cursor: Vec2,
     shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    camera: Vec2,
+    zoom: f32,
+    #[serde(default)]
+    position: Vec3,
 }
 
+impl Preset for State {}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
-            elapsed: Duration::ZERO,
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            camera: vec2(100.0, -100.0),
-            zoom: 10.0,
             mouse_button_pressed: false,
+            fly_camera: FlyCamera::new(),
 
             scroll: 1.0,
             drag: Vec2::ZERO,
             prev_cursor: Vec2::ZERO,
             shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                camera: vec2(100.0, -100.0),
+                zoom: 10.0,
+                position: Vec3::ZERO,
+            },
         }
     }
 
@@ -87,29 +103,47 @@ impl crate::controller::Controller for Controller {
         };
     }
 
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        self.fly_camera.keyboard_input(input);
+    }
+
+    fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.fly_camera.modifiers_changed(modifiers);
+    }
+
+    fn focus_lost(&mut self) {
+        self.fly_camera.release_keys();
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size.width = size.width;
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
-        self.elapsed = self.start.elapsed();
-        self.zoom *= self.scroll;
-        self.camera += self.drag;
+    fn update(&mut self, time: f32) {
+        self.state.zoom *= self.scroll;
+        self.state.camera += self.drag;
+        // Same view rotation as the shader, so W flies where the camera looks
+        let translate =
+            -(self.state.camera + self.drag_start - self.drag_end) / self.size.height as f32 * PI;
+        let rm = Mat3::from_rotation_y(translate.x).mul_mat3(&Mat3::from_rotation_x(translate.y));
+        self.state.position += rm.mul_vec3(self.fly_camera.translation());
         self.shader_constants = ShaderConstants {
             width: self.size.width,
             height: self.size.height,
-            time: self.elapsed.as_secs_f32(),
+            time,
             cursor_x: self.cursor.x,
             cursor_y: self.cursor.y,
             drag_start_x: self.drag_start.x,
             drag_start_y: self.drag_start.y,
             drag_end_x: self.drag_end.x,
             drag_end_y: self.drag_end.y,
-            zoom: self.zoom,
-            translate_x: self.camera.x,
-            translate_y: self.camera.y,
+            zoom: self.state.zoom,
+            translate_x: self.state.camera.x,
+            translate_y: self.state.camera.y,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
+            position: self.state.position.into(),
+            jitter: self.shader_constants.jitter,
         };
         self.finish_update();
     }
@@ -117,6 +151,19 @@ impl crate::controller::Controller for Controller {
     fn push_constants(&self) -> &[u8] {
         bytemuck::bytes_of(&self.shader_constants)
     }
+
+    fn set_jitter(&mut self, jitter: glam::Vec2) {
+        self.shader_constants.jitter = jitter.into();
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
 }
 
 impl Controller {
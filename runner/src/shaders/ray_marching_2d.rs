@@ -1,46 +1,55 @@
+use crate::{
+    navigation::{key_navigation, KeyNavigation},
+    presets::{self, Preset},
+};
 use bytemuck::Zeroable;
 use egui::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::ray_marching_2d::ShaderConstants;
-use std::time::{Duration, Instant};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta},
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta},
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
-    elapsed: Duration,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
-    camera: Vec2,
-    zoom: f32,
     mouse_button_pressed: bool,
 
     scroll: f32,
     drag: Vec2,
     prev_cursor: Vec2,
     shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    camera: Vec2,
+    zoom: f32,
 }
 
+impl Preset for State {}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
-            elapsed: Duration::ZERO,
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            camera: Vec2::ZERO,
-            zoom: 1.0,
             mouse_button_pressed: false,
 
             scroll: 1.0,
             drag: Vec2::ZERO,
             prev_cursor: Vec2::ZERO,
             shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                camera: Vec2::ZERO,
+                zoom: 1.0,
+            },
         }
     }
 
@@ -87,29 +96,38 @@ impl crate::controller::Controller for Controller {
         };
     }
 
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        match key_navigation(input) {
+            Some(KeyNavigation::Pan(offset)) => self.state.camera += vec2(offset.x, offset.y),
+            Some(KeyNavigation::Zoom(lines)) => {
+                self.mouse_scroll(MouseScrollDelta::LineDelta(0.0, lines))
+            }
+            None => {}
+        }
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size.width = size.width;
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
-        self.elapsed = self.start.elapsed();
-        self.zoom *= self.scroll;
-        self.camera *= 1.0 / self.scroll;
-        self.camera += self.drag;
+    fn update(&mut self, time: f32) {
+        self.state.zoom *= self.scroll;
+        self.state.camera *= 1.0 / self.scroll;
+        self.state.camera += self.drag;
         self.shader_constants = ShaderConstants {
             width: self.size.width,
             height: self.size.height,
-            time: self.elapsed.as_secs_f32(),
+            time,
             cursor_x: self.cursor.x,
             cursor_y: self.cursor.y,
             drag_start_x: self.drag_start.x,
             drag_start_y: self.drag_start.y,
             drag_end_x: self.drag_end.x,
             drag_end_y: self.drag_end.y,
-            zoom: self.zoom,
-            translate_x: self.camera.x,
-            translate_y: self.camera.y,
+            zoom: self.state.zoom,
+            translate_x: self.state.camera.x,
+            translate_y: self.state.camera.y,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
         };
         self.finish_update();
@@ -118,6 +136,15 @@ impl crate::controller::Controller for Controller {
     fn push_constants(&self) -> &[u8] {
         bytemuck::bytes_of(&self.shader_constants)
     }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
 }
 
 impl Controller {
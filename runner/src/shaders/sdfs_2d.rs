@@ -1,45 +1,82 @@
 use bytemuck::Zeroable;
 use egui::{Context, CursorIcon};
 use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::sdfs_2d::{Params, ShaderConstants, Shape};
-use std::{
-    f32::consts::PI,
-    time::{Duration, Instant},
-};
+use std::f32::consts::PI;
 use strum::IntoEnumIterator;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta},
+    event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta},
     event_loop::EventLoopProxy,
 };
 
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    navigation::{key_navigation, KeyNavigation},
+    presets::{self, Preset},
+    window::UserEvent,
+};
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
-    elapsed: Duration,
     cursor: Vec2,
     mouse_button_pressed: bool,
     can_drag: Option<usize>,
     drag_point: Option<usize>,
+    /// Scrolling zooms instead of rotating the shape
+    ctrl: bool,
+    state: State,
+    shader_constants: ShaderConstants,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    #[param(radio)]
     shape: Shape,
+    /// Indexed by `Shape`
     params: Vec<Params>,
-    shader_constants: ShaderConstants,
+    #[serde(default = "default_zoom")]
+    zoom: f32,
+    #[serde(default)]
+    translate: Vec2,
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        let shapes = Shape::iter().count();
+        if self.params.len() != shapes {
+            return Err(format!(
+                "expected params for {shapes} shapes, got {}",
+                self.params.len()
+            ));
+        }
+        if !self.zoom.is_finite() || self.zoom <= 0.0 {
+            return Err(format!("zoom must be positive, got {}", self.zoom));
+        }
+        Ok(())
+    }
 }
 
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
-            elapsed: Duration::ZERO,
             cursor: Vec2::ZERO,
             mouse_button_pressed: false,
             can_drag: None,
             drag_point: None,
-            shape: Shape::Circle,
-            params: Shape::iter().map(|shape| shape.params()).collect(),
+            ctrl: false,
+            state: State {
+                shape: Shape::Circle,
+                params: Shape::iter().map(|shape| shape.params()).collect(),
+                zoom: default_zoom(),
+                translate: Vec2::ZERO,
+            },
             shader_constants: ShaderConstants::zeroed(),
         }
     }
@@ -61,19 +98,21 @@ impl crate::controller::Controller for Controller {
 
     fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
         self.cursor = vec2(position.x as f32, position.y as f32);
-        let num_points = self.shape.spec().num_points;
+        let num_points = self.state.shape.spec().num_points;
         if let Some(i) = self.drag_point {
-            self.params[self.shape as usize].ps[i] = rotate(
+            self.state.params[self.state.shape as usize].ps[i] = rotate(
                 self.from_pixels(self.cursor),
-                self.params[self.shape as usize].rot,
+                self.state.params[self.state.shape as usize].rot,
             )
             .into();
         } else if num_points > 0 {
-            self.can_drag = self.params[self.shape as usize].ps[0..num_points as usize]
+            self.can_drag = self.state.params[self.state.shape as usize].ps[0..num_points as usize]
                 .iter()
                 .position(|p| {
-                    (rotate((*p).into(), -self.params[self.shape as usize].rot)
-                        - self.from_pixels(self.cursor))
+                    (rotate(
+                        (*p).into(),
+                        -self.state.params[self.state.shape as usize].rot,
+                    ) - self.from_pixels(self.cursor))
                     .length()
                         < 0.01
                 });
@@ -81,13 +120,35 @@ impl crate::controller::Controller for Controller {
     }
 
     fn mouse_scroll(&mut self, delta: MouseScrollDelta) {
-        self.params[self.shape as usize].rot += PI / 30.0
-            * match delta {
-                MouseScrollDelta::LineDelta(_, y) => y,
-                MouseScrollDelta::PixelDelta(p) => {
-                    (1.0 + p.y.abs() as f32).ln() * p.y.signum() as f32
-                }
-            };
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(p) => (1.0 + p.y.abs() as f32).ln() * p.y.signum() as f32,
+        };
+        if self.ctrl {
+            self.pinch(1.1_f32.powf(lines));
+        } else {
+            self.state.params[self.state.shape as usize].rot += PI / 30.0 * lines;
+        }
+    }
+
+    fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.ctrl = modifiers.ctrl();
+    }
+
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        match key_navigation(input) {
+            Some(KeyNavigation::Pan(offset)) => self.pan(-offset),
+            Some(KeyNavigation::Zoom(lines)) => self.pinch(1.1_f32.powf(lines)),
+            None => {}
+        }
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        self.state.zoom /= scale;
+    }
+
+    fn pan(&mut self, delta: Vec2) {
+        self.state.translate -= vec2(delta.x, -delta.y) / self.size.height as f32 * self.state.zoom;
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -95,16 +156,17 @@ impl crate::controller::Controller for Controller {
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
-        self.elapsed = self.start.elapsed();
+    fn update(&mut self, time: f32) {
         self.shader_constants = ShaderConstants {
             size: self.size.into(),
-            time: self.elapsed.as_secs_f32(),
+            time,
             cursor: self.cursor.into(),
             mouse_button_pressed: !(1
                 << (self.mouse_button_pressed && self.drag_point.is_none()) as u32),
-            shape: self.shape as u32,
-            params: self.params[self.shape as usize],
+            shape: self.state.shape as u32,
+            params: self.state.params[self.state.shape as usize],
+            zoom: self.state.zoom,
+            translate: self.state.translate.into(),
         };
     }
 
@@ -112,8 +174,28 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn accumulation_key(&self) -> Vec<u8> {
+        // The shader doesn't animate, so the frames add up while time passes
+        let constants = ShaderConstants {
+            time: 0.0,
+            ..self.shader_constants
+        };
+        bytemuck::bytes_of(&constants).to_vec()
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.can_drag = None;
+        self.drag_point = None;
+        Ok(())
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 
     fn ui(&mut self, ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
@@ -124,12 +206,10 @@ impl crate::controller::Controller for Controller {
         } else {
             CursorIcon::Default
         });
-        for shape in Shape::iter() {
-            ui.radio_value(&mut self.shape, shape, shape.to_string());
-        }
-        let spec = self.shape.spec();
+        self.state.params_ui(ui);
+        let spec = self.state.shape.spec();
         if spec.num_dims > 0 {
-            let params = &mut self.params[self.shape as usize];
+            let params = &mut self.state.params[self.state.shape as usize];
             let (dim1_max, dim2_max, dim1_label, dim2_label) = {
                 if spec.is_radial {
                     (0.5, params.dim.x, "Radius", "Radius2")
@@ -169,6 +249,8 @@ impl Controller {
         let p = vec2(p.x, -p.y);
         (p - 0.5 * vec2(self.size.width as f32, -(self.size.height as f32)))
             / self.size.height as f32
+            * self.state.zoom
+            + self.state.translate
     }
 }
 
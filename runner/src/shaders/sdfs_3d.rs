@@ -1,56 +1,82 @@
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    navigation::FlyCamera,
+    presets::{self, Preset},
+    window::UserEvent,
+};
 use bytemuck::Zeroable;
 use egui::{Context, CursorIcon};
 use glam::{vec2, Mat3, Vec2, Vec3, Vec3Swizzles};
+use serde::{Deserialize, Serialize};
 use shared::{
     from_pixels,
     push_constants::sdfs_3d::{sdf_shape, sdf_slice, Params, ShaderConstants, Shape},
 };
-use std::{
-    f32::consts::PI,
-    time::{Duration, Instant},
-};
+use std::f32::consts::PI;
 use strum::IntoEnumIterator;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta},
+    event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta},
     event_loop::EventLoopProxy,
 };
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
-    elapsed: Duration,
     mouse_button_pressed: u32,
     cursor: Vec2,
     prev_cursor: Vec2,
     can_drag: Option<usize>,
     drag_point: Option<usize>,
+    state: State,
+    shader_constants: ShaderConstants,
+    cursor_3d_pos: Vec3,
+    fly_camera: FlyCamera,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    #[param(radio)]
     shape: Shape,
+    /// Indexed by `Shape`
     params: Vec<Params>,
-    shader_constants: ShaderConstants,
     camera: Vec2,
     slice_z: f32,
-    cursor_3d_pos: Vec3,
+    #[serde(default)]
+    position: Vec3,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        let shapes = Shape::iter().count();
+        if self.params.len() != shapes {
+            return Err(format!(
+                "expected params for {shapes} shapes, got {}",
+                self.params.len()
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
-            elapsed: Duration::ZERO,
             cursor: Vec2::ZERO,
             prev_cursor: Vec2::ZERO,
             mouse_button_pressed: 0,
             can_drag: None,
             drag_point: None,
-            shape: Shape::Sphere,
-            params: Shape::iter().map(|shape| shape.params()).collect(),
+            state: State {
+                shape: Shape::Sphere,
+                params: Shape::iter().map(|shape| shape.params()).collect(),
+                camera: vec2(0.2, -0.1),
+                slice_z: 0.0,
+                position: Vec3::ZERO,
+            },
             shader_constants: ShaderConstants::zeroed(),
-            camera: vec2(0.2, -0.1),
-            slice_z: 0.0,
             cursor_3d_pos: Vec3::ZERO,
+            fly_camera: FlyCamera::new(),
         }
     }
 
@@ -80,32 +106,32 @@ impl crate::controller::Controller for Controller {
 
     fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
         self.cursor = vec2(position.x as f32, position.y as f32);
-        let num_points = self.shape.spec().num_points;
-        let translate = self.camera;
+        let num_points = self.state.shape.spec().num_points;
+        let translate = self.state.camera;
         let rm = Mat3::from_rotation_y(translate.x).mul_mat3(&Mat3::from_rotation_x(translate.y));
         if let Some(i) = self.drag_point {
             let pc = from_pixels(self.prev_cursor, self.size.into());
             let cc = from_pixels(self.cursor, self.size.into());
             let t = rm.mul_vec3((cc - pc).extend(0.0));
-            let p: Vec3 = self.params[self.shape as usize].ps[i].into();
-            self.params[self.shape as usize].ps[i] = (p + t).into();
+            let p: Vec3 = self.state.params[self.state.shape as usize].ps[i].into();
+            self.state.params[self.state.shape as usize].ps[i] = (p + t).into();
         } else if num_points > 0 {
-            let ro = rm.mul_vec3(-Vec3::Z);
+            let ro = rm.mul_vec3(-Vec3::Z) + self.state.position;
             let rd = rm
                 .mul_vec3(from_pixels(self.cursor, self.size.into()).extend(1.0))
                 .normalize();
-            self.can_drag = self.params[self.shape as usize].ps[0..num_points as usize]
+            self.can_drag = self.state.params[self.state.shape as usize].ps[0..num_points as usize]
                 .iter()
                 .position(|p| ray_intersects_point(ro, rd, (*p).into(), 0.05));
         }
         if self.mouse_button_pressed & (1 << 2) != 0 {
-            self.camera += PI * (self.cursor - self.prev_cursor) / self.size.height as f32;
+            self.state.camera += PI * (self.cursor - self.prev_cursor) / self.size.height as f32;
         }
         self.prev_cursor = self.cursor;
     }
 
     fn mouse_scroll(&mut self, delta: MouseScrollDelta) {
-        self.slice_z += match delta {
+        self.state.slice_z += match delta {
             MouseScrollDelta::LineDelta(_, y) => 0.01 * y,
             MouseScrollDelta::PixelDelta(p) => {
                 0.01 * (1.0 + p.y.abs() as f32).ln() * p.y.signum() as f32
@@ -113,30 +139,40 @@ impl crate::controller::Controller for Controller {
         };
     }
 
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        self.fly_camera.keyboard_input(input);
+    }
+
+    fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.fly_camera.modifiers_changed(modifiers);
+    }
+
+    fn focus_lost(&mut self) {
+        self.fly_camera.release_keys();
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size.width = size.width;
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
-        self.elapsed = self.start.elapsed();
-
+    fn update(&mut self, time: f32) {
         const MAX_STEPS: u32 = 100;
         const MAX_DIST: f32 = 100.0;
         const SURF_DIST: f32 = 0.0001;
+        let rm = Mat3::from_rotation_y(self.state.camera.x)
+            .mul_mat3(&Mat3::from_rotation_x(self.state.camera.y));
+        self.state.position += rm.mul_vec3(self.fly_camera.translation());
         // TODO: probably an analytical solution for this
         self.cursor_3d_pos = {
             let cursor = from_pixels(self.cursor, self.size.into());
-            let translate = self.camera;
-            let rm =
-                Mat3::from_rotation_y(translate.x).mul_mat3(&Mat3::from_rotation_x(translate.y));
-            let ro = rm.mul_vec3(-Vec3::Z);
+            let ro = rm.mul_vec3(-Vec3::Z) + self.state.position;
             let rd = rm.mul_vec3(cursor.extend(1.0)).normalize();
             let mut d0 = 0.0;
 
             for _ in 0..MAX_STEPS {
                 let p = ro + rd * d0;
-                let ds = sdf_slice(p, self.slice_z).abs();
+                let ds = sdf_slice(p, self.state.slice_z).abs();
                 d0 += ds;
                 if d0 > MAX_DIST {
                     break;
@@ -145,12 +181,20 @@ impl crate::controller::Controller for Controller {
                     break;
                 }
             }
-            let mut p = (ro + rd * d0).xy().extend(self.slice_z);
+            let mut p = (ro + rd * d0).xy().extend(self.state.slice_z);
 
-            let mut d = sdf_shape(p, self.shape, self.params[self.shape as usize]);
+            let mut d = sdf_shape(
+                p,
+                self.state.shape,
+                self.state.params[self.state.shape as usize],
+            );
             while d > 1.0 {
-                p = (p.xy() + (-p.xy()).normalize() * (d - 1.0)).extend(self.slice_z);
-                d = sdf_shape(p, self.shape, self.params[self.shape as usize]);
+                p = (p.xy() + (-p.xy()).normalize() * (d - 1.0)).extend(self.state.slice_z);
+                d = sdf_shape(
+                    p,
+                    self.state.shape,
+                    self.state.params[self.state.shape as usize],
+                );
             }
 
             p
@@ -158,17 +202,19 @@ impl crate::controller::Controller for Controller {
 
         self.shader_constants = ShaderConstants {
             size: self.size.into(),
-            time: self.elapsed.as_secs_f32(),
+            time,
             cursor: self.cursor_3d_pos.into(),
             mouse_button_pressed: if self.drag_point.is_some() {
                 self.mouse_button_pressed & !1
             } else {
                 self.mouse_button_pressed
             },
-            slice_z: self.slice_z,
-            translate: self.camera.into(),
-            shape: self.shape as u32,
-            params: self.params[self.shape as usize],
+            slice_z: self.state.slice_z,
+            translate: self.state.camera.into(),
+            position: self.state.position.into(),
+            shape: self.state.shape as u32,
+            params: self.state.params[self.state.shape as usize],
+            jitter: self.shader_constants.jitter,
         };
     }
 
@@ -176,8 +222,32 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn accumulation_key(&self) -> Vec<u8> {
+        // The shader doesn't animate, so the frames add up while time passes
+        let constants = ShaderConstants {
+            time: 0.0,
+            ..self.shader_constants
+        };
+        bytemuck::bytes_of(&constants).to_vec()
+    }
+
+    fn set_jitter(&mut self, jitter: Vec2) {
+        self.shader_constants.jitter = jitter.into();
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.can_drag = None;
+        self.drag_point = None;
+        Ok(())
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 
     fn ui(&mut self, ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
@@ -188,12 +258,10 @@ impl crate::controller::Controller for Controller {
         } else {
             CursorIcon::Default
         });
-        for shape in Shape::iter() {
-            ui.radio_value(&mut self.shape, shape, shape.to_string());
-        }
-        let spec = self.shape.spec();
+        self.state.params_ui(ui);
+        let spec = self.state.shape.spec();
         if spec.num_dims > 0 {
-            let params = &mut self.params[self.shape as usize];
+            let params = &mut self.state.params[self.state.shape as usize];
             let (dim1_max, dim2_max, dim1_label, dim2_label) = {
                 if spec.is_radial {
                     (0.5, params.dim.x, "Radius", "Radius2")
@@ -234,7 +302,7 @@ impl crate::controller::Controller for Controller {
                     );
                 });
             }
-            if self.shape == Shape::CuboidFrame {
+            if self.state.shape == Shape::CuboidFrame {
                 ui.horizontal(|ui| {
                     ui.label("Inner Width");
                     ui.add(
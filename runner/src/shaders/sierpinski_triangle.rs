@@ -1,45 +1,72 @@
+use crate::{
+    navigation::{key_navigation, KeyNavigation},
+    presets::{self, Preset},
+};
 use bytemuck::Zeroable;
+use serde::{Deserialize, Serialize};
 use shared::push_constants::sierpinski_triangle::ShaderConstants;
-use winit::{dpi::PhysicalSize, event::MouseScrollDelta};
+use winit::{
+    dpi::PhysicalSize,
+    event::{KeyboardInput, MouseScrollDelta},
+};
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    scroll: f64,
     zoom: f32,
     shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    scroll: f64,
 }
 
+impl Preset for State {}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            scroll: 0.0,
             zoom: 0.0,
             shader_constants: ShaderConstants::zeroed(),
+            state: State { scroll: 0.0 },
         }
     }
 
     fn mouse_scroll(&mut self, delta: MouseScrollDelta) {
-        self.scroll += match delta {
+        self.state.scroll += match delta {
             MouseScrollDelta::LineDelta(_, y) => y as f64,
             MouseScrollDelta::PixelDelta(p) => 0.15 * (1.0 + p.y.abs()).ln() * p.y.signum(),
         };
     }
 
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        // The view is fixed on a point of the fractal, so only zooming applies
+        if let Some(KeyNavigation::Zoom(lines)) = key_navigation(input) {
+            self.mouse_scroll(MouseScrollDelta::LineDelta(0.0, lines));
+        }
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        // Inverse of `zoom = 0.85^scroll`
+        self.state.scroll -= (scale as f64).ln() / 0.85_f64.ln();
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size.width = size.width;
         self.size.height = size.height;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, _time: f32) {
         let c = 59.87868500430847;
         let v = 34.102688577484;
-        let scroll = if self.scroll > c {
-            self.scroll - v * (1.0 + ((self.scroll - c) / v).floor())
-        } else if self.scroll < -1.0 {
-            -1.0 - (-self.scroll).log10()
+        let scroll = if self.state.scroll > c {
+            self.state.scroll - v * (1.0 + ((self.state.scroll - c) / v).floor())
+        } else if self.state.scroll < -1.0 {
+            -1.0 - (-self.state.scroll).log10()
         } else {
-            self.scroll
+            self.state.scroll
         };
         self.zoom = 0.85_f64.powf(scroll) as f32;
 
@@ -55,4 +82,13 @@ impl crate::controller::Controller for Controller {
     fn push_constants(&self) -> &[u8] {
         bytemuck::bytes_of(&self.shader_constants)
     }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
 }
@@ -1,9 +1,14 @@
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    presets::{self, Preset},
+    window::UserEvent,
+};
 use bytemuck::Zeroable;
 use egui::{Color32, Context, Rect, RichText, Sense, Stroke, Ui};
 use glam::{vec2, Quat, Vec2};
+use serde::{Deserialize, Serialize};
 use shared::push_constants::spherical_harmonics::{ShaderConstants, Variant};
-use std::{f32::consts::PI, time::Instant};
+use std::f32::consts::PI;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, MouseButton, MouseScrollDelta},
@@ -12,38 +17,64 @@ use winit::{
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
+    time: f32,
+    start_time: f32,
     cursor: Vec2,
     drag_start: Vec2,
     drag_end: Vec2,
-    quat: Quat,
-    zoom: f32,
     mouse_button_pressed: bool,
     shader_constants: ShaderConstants,
+    negative_m: bool,
+    state: State,
+}
+
+/// Largest `l` the picker offers
+const L_MAX: u32 = 9;
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    quat: Quat,
+    zoom: f32,
     l: u32,
     m: i32,
-    negative_m: bool,
-    include_time_factor: bool,
+    #[param(radio)]
     variant: Variant,
+    #[param(label = "Include time factor")]
+    include_time_factor: bool,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if self.l > L_MAX {
+            return Err(format!("l must be at most {L_MAX}, got {}", self.l));
+        }
+        if self.m.unsigned_abs() > self.l {
+            return Err(format!("m must be from -l to l, got {}", self.m));
+        }
+        Ok(())
+    }
 }
 
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         Self {
             size,
-            start: Instant::now(),
+            time: 0.0,
+            start_time: 0.0,
             cursor: Vec2::ZERO,
             drag_start: Vec2::ZERO,
             drag_end: Vec2::ZERO,
-            quat: Quat::IDENTITY,
-            zoom: 1.0,
             mouse_button_pressed: false,
             shader_constants: ShaderConstants::zeroed(),
-            l: 2,
-            m: 1,
             negative_m: false,
-            include_time_factor: false,
-            variant: Variant::Real,
+            state: State {
+                quat: Quat::IDENTITY,
+                zoom: 1.0,
+                l: 2,
+                m: 1,
+                variant: Variant::Real,
+                include_time_factor: false,
+            },
         }
     }
 
@@ -53,7 +84,8 @@ impl crate::controller::Controller for Controller {
                 ElementState::Pressed => true,
                 ElementState::Released => {
                     let angles = PI * (self.drag_start - self.drag_end) / self.size.height as f32;
-                    self.quat = self
+                    self.state.quat = self
+                        .state
                         .quat
                         .mul_quat(Quat::from_rotation_y(-angles.x))
                         .mul_quat(Quat::from_rotation_x(angles.y))
@@ -93,33 +125,47 @@ impl crate::controller::Controller for Controller {
                 }
             }
         };
-        self.zoom *= scroll;
+        self.state.zoom *= scroll;
+    }
+
+    fn pinch(&mut self, scale: f32) {
+        self.state.zoom /= scale;
+    }
+
+    fn rotate(&mut self, angle: f32) {
+        self.state.quat = self
+            .state
+            .quat
+            .mul_quat(Quat::from_rotation_z(angle))
+            .normalize();
     }
 
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size = size;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, time: f32) {
+        self.time = time;
         let angles = PI * (self.drag_start - self.drag_end) / self.size.height as f32;
         let quat = self
+            .state
             .quat
             .mul_quat(Quat::from_rotation_y(-angles.x))
             .mul_quat(Quat::from_rotation_x(angles.y));
         self.shader_constants = ShaderConstants {
             size: self.size.into(),
-            time: if self.include_time_factor {
-                self.start.elapsed().as_secs_f32()
+            time: if self.state.include_time_factor {
+                time - self.start_time
             } else {
                 0.0
             },
             cursor: self.cursor.into(),
-            zoom: self.zoom,
+            zoom: self.state.zoom,
             mouse_button_pressed: !(1 << self.mouse_button_pressed as u32),
-            l: self.l,
-            m: self.m,
+            l: self.state.l,
+            m: self.state.m,
             quat: quat.into(),
-            variant: self.variant as u32,
+            variant: self.state.variant as u32,
         };
     }
 
@@ -127,34 +173,40 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.negative_m = self.state.m < 0;
+        self.start_time = self.time;
+        Ok(())
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 
     fn ui(&mut self, ctx: &Context, ui: &mut Ui, _: &EventLoopProxy<UserEvent>) {
-        ui.radio_value(&mut self.variant, Variant::Real, "Real");
-        ui.radio_value(&mut self.variant, Variant::Complex, "Complex");
-        if ui
-            .checkbox(&mut self.include_time_factor, "Include time factor")
-            .clicked()
-            && self.include_time_factor
-        {
-            self.start = Instant::now();
+        let include_time_factor = self.state.include_time_factor;
+        if self.state.params_ui(ui) && self.state.include_time_factor && !include_time_factor {
+            self.start_time = self.time;
         }
 
         let (rect, response) = ui.allocate_at_least([220.0; 2].into(), Sense::drag());
-        let l_max = 9;
+        let l_max = L_MAX;
 
         if let Some(mouse_pos) = response.interact_pointer_pos() {
             let v = ((mouse_pos - rect.left_top()) * (l_max + 1) as f32 / rect.width())
                 .clamp(egui::Vec2::ZERO, egui::Vec2::splat(l_max as f32));
             if v.x > v.y {
                 let dif = v.x - v.y;
-                self.l = (v.y + (dif / 2.0)) as u32;
-                self.m = (v.x - (dif / 2.0)) as i32;
+                self.state.l = (v.y + (dif / 2.0)) as u32;
+                self.state.m = (v.x - (dif / 2.0)) as i32;
             } else {
-                self.l = v.y as u32;
-                self.m = v.x as i32;
+                self.state.l = v.y as u32;
+                self.state.m = v.x as i32;
             }
             ctx.input(|input| {
                 if input.pointer.any_pressed() {
@@ -162,7 +214,7 @@ impl crate::controller::Controller for Controller {
                 }
             });
             if self.negative_m {
-                self.m = -self.m;
+                self.state.m = -self.state.m;
             }
         }
 
@@ -176,9 +228,9 @@ impl crate::controller::Controller for Controller {
                 ui.painter().circle(
                     circle_pos,
                     circle_radius,
-                    if l == self.l && m == self.m {
+                    if l == self.state.l && m == self.state.m {
                         Color32::DARK_GREEN
-                    } else if l == self.l && m == -self.m {
+                    } else if l == self.state.l && m == -self.state.m {
                         Color32::from_rgb(0, 0x64, 0x64)
                     } else {
                         Color32::DARK_GRAY
@@ -196,8 +248,8 @@ impl crate::controller::Controller for Controller {
                     ui.spacing_mut().item_spacing *= 0.0;
                     ui.heading(RichText::new("Y").size(text_size));
                     ui.vertical(|ui| {
-                        ui.label(RichText::new(format!(" {}", self.m)).size(text_size / 2.0));
-                        ui.label(RichText::new(format!("{}", self.l)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!(" {}", self.state.m)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!("{}", self.state.l)).size(text_size / 2.0));
                     });
                     ui.heading(RichText::new("(θ, φ)").size(text_size))
                 })
@@ -1,17 +1,18 @@
-use crate::model::Vertex;
-use crate::window::UserEvent;
+use crate::{
+    controller::ParamsUi,
+    model::Vertex,
+    presets::{self, Preset},
+    window::UserEvent,
+};
 use bytemuck::Zeroable;
 use egui::{Color32, Context, Rect, RichText, Sense, Stroke, Ui};
 use glam::{vec2, vec3, Quat, Vec2, Vec3, Vec3Swizzles};
+use serde::{Deserialize, Serialize};
 use shared::{
     push_constants::spherical_harmonics_shape::{ShaderConstants, Variant},
     spherical_harmonics::*,
 };
-use std::{
-    f32::consts::{FRAC_1_SQRT_2, PI, TAU},
-    time::Instant,
-};
-use strum::IntoEnumIterator;
+use std::f32::consts::{FRAC_1_SQRT_2, PI, TAU};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, MouseButton, MouseScrollDelta},
@@ -20,21 +21,42 @@ use winit::{
 
 pub struct Controller {
     size: PhysicalSize<u32>,
-    start: Instant,
     cursor: Vec2,
     last_cursor: Vec2,
-    rot: Quat,
     mouse_button_pressed: bool,
     shader_constants: ShaderConstants,
     buffers: (Vec<Vertex>, Vec<u32>),
     camera: crate::camera::Camera,
+    negative_m: bool,
+    state: State,
+}
+
+/// Largest `l` the picker offers
+const L_MAX: u32 = 9;
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    rot: Quat,
     l: u32,
     m: i32,
+    #[param(radio)]
     variant: Variant,
-    negative_m: bool,
+    #[param(label = "Include time factor")]
     include_time_factor: bool,
 }
 
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if self.l > L_MAX {
+            return Err(format!("l must be at most {L_MAX}, got {}", self.l));
+        }
+        if self.m.unsigned_abs() > self.l {
+            return Err(format!("m must be from -l to l, got {}", self.m));
+        }
+        Ok(())
+    }
+}
+
 impl crate::controller::Controller for Controller {
     fn new(size: PhysicalSize<u32>) -> Self {
         let l = 2;
@@ -43,10 +65,8 @@ impl crate::controller::Controller for Controller {
 
         Self {
             size,
-            start: Instant::now(),
             cursor: Vec2::ZERO,
             last_cursor: Vec2::ZERO,
-            rot: Quat::IDENTITY,
             mouse_button_pressed: false,
             shader_constants: ShaderConstants::zeroed(),
             buffers: create_buffers(m, l, variant),
@@ -59,11 +79,14 @@ impl crate::controller::Controller for Controller {
                 znear: 0.1,
                 zfar: 100.0,
             },
-            l,
-            m,
-            variant,
             negative_m: false,
-            include_time_factor: false,
+            state: State {
+                rot: Quat::IDENTITY,
+                l,
+                m,
+                variant,
+                include_time_factor: false,
+            },
         }
     }
 
@@ -86,7 +109,9 @@ impl crate::controller::Controller for Controller {
                 angles.x * (e.x + e.z),
                 angles.dot(e.yx()),
             );
-            self.rot = Quat::from_scaled_axis(p).mul_quat(self.rot).normalize();
+            self.state.rot = Quat::from_scaled_axis(p)
+                .mul_quat(self.state.rot)
+                .normalize();
         }
         self.last_cursor = self.cursor;
     }
@@ -113,14 +138,26 @@ impl crate::controller::Controller for Controller {
         self.camera.eye *= scroll;
     }
 
+    fn pinch(&mut self, scale: f32) {
+        self.camera.eye /= scale;
+    }
+
+    fn rotate(&mut self, angle: f32) {
+        // Spin around the line of sight
+        let axis = (self.camera.target - self.camera.eye).normalize();
+        self.state.rot = Quat::from_axis_angle(axis, -angle)
+            .mul_quat(self.state.rot)
+            .normalize();
+    }
+
     fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size = size;
         self.camera.aspect = size.width as f32 / size.height as f32;
     }
 
-    fn update(&mut self) {
+    fn update(&mut self, _time: f32) {
         self.shader_constants = ShaderConstants {
-            rot: self.rot.into(),
+            rot: self.state.rot.into(),
             view_proj: self.camera.build_view_projection_matrix().into(),
         };
     }
@@ -129,45 +166,43 @@ impl crate::controller::Controller for Controller {
         bytemuck::bytes_of(&self.shader_constants)
     }
 
-    fn has_ui(&self) -> bool {
-        true
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.negative_m = self.state.m < 0;
+        self.buffers = create_buffers(self.state.m, self.state.l, self.state.variant);
+        Ok(())
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
     }
 
     fn ui(&mut self, ctx: &Context, ui: &mut Ui, event_proxy: &EventLoopProxy<UserEvent>) {
-        for variant in Variant::iter() {
-            if ui
-                .radio(self.variant == variant, variant.to_string())
-                .clicked()
-                && self.variant != variant
-            {
-                self.variant = variant;
-                self.buffers = create_buffers(self.m, self.l, self.variant);
-                signal_new_vertices(event_proxy);
-            }
-        }
-        if ui
-            .checkbox(&mut self.include_time_factor, "Include time factor")
-            .clicked()
-            && self.include_time_factor
-        {
-            self.start = Instant::now();
+        let variant = self.state.variant;
+        if self.state.params_ui(ui) && self.state.variant != variant {
+            self.buffers = create_buffers(self.state.m, self.state.l, self.state.variant);
+            signal_new_vertices(event_proxy);
         }
 
         let (rect, response) = ui.allocate_at_least([220.0; 2].into(), Sense::drag());
-        let l_max = 9;
+        let l_max = L_MAX;
 
         if let Some(mouse_pos) = response.interact_pointer_pos() {
             let v = ((mouse_pos - rect.left_top()) * (l_max + 1) as f32 / rect.width())
                 .clamp(egui::Vec2::ZERO, egui::Vec2::splat(l_max as f32));
-            let prev_l = self.l;
-            let prev_m = self.m;
+            let prev_l = self.state.l;
+            let prev_m = self.state.m;
             if v.x > v.y {
                 let dif = v.x - v.y;
-                self.l = (v.y + (dif / 2.0)) as u32;
-                self.m = (v.x - (dif / 2.0)) as i32;
+                self.state.l = (v.y + (dif / 2.0)) as u32;
+                self.state.m = (v.x - (dif / 2.0)) as i32;
             } else {
-                self.l = v.y as u32;
-                self.m = v.x as i32;
+                self.state.l = v.y as u32;
+                self.state.m = v.x as i32;
             }
             ctx.input(|input| {
                 if input.pointer.any_pressed() {
@@ -175,10 +210,10 @@ impl crate::controller::Controller for Controller {
                 }
             });
             if self.negative_m {
-                self.m = -self.m;
+                self.state.m = -self.state.m;
             }
-            if prev_l != self.l || prev_m != self.m {
-                self.buffers = create_buffers(self.m, self.l, self.variant);
+            if prev_l != self.state.l || prev_m != self.state.m {
+                self.buffers = create_buffers(self.state.m, self.state.l, self.state.variant);
                 signal_new_vertices(event_proxy)
             }
         }
@@ -193,9 +228,9 @@ impl crate::controller::Controller for Controller {
                 ui.painter().circle(
                     circle_pos,
                     circle_radius,
-                    if l == self.l && m == self.m {
+                    if l == self.state.l && m == self.state.m {
                         Color32::DARK_GREEN
-                    } else if l == self.l && m == -self.m {
+                    } else if l == self.state.l && m == -self.state.m {
                         Color32::from_rgb(0, 0x64, 0x64)
                     } else {
                         Color32::DARK_GRAY
@@ -213,8 +248,8 @@ impl crate::controller::Controller for Controller {
                     ui.spacing_mut().item_spacing *= 0.0;
                     ui.heading(RichText::new("Y").size(text_size));
                     ui.vertical(|ui| {
-                        ui.label(RichText::new(format!(" {}", self.m)).size(text_size / 2.0));
-                        ui.label(RichText::new(format!("{}", self.l)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!(" {}", self.state.m)).size(text_size / 2.0));
+                        ui.label(RichText::new(format!("{}", self.state.l)).size(text_size / 2.0));
                     });
                     ui.heading(RichText::new("(θ, φ)").size(text_size))
                 })
@@ -0,0 +1,119 @@
+use crate::{
+    controller::ParamsUi,
+    presets::{self, Preset},
+    window::UserEvent,
+};
+use bytemuck::Zeroable;
+use egui::Context;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use shared::push_constants::trails::ShaderConstants;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, MouseButton},
+    event_loop::EventLoopProxy,
+};
+
+pub struct Controller {
+    size: PhysicalSize<u32>,
+    cursor: Vec2,
+    drawing: bool,
+    time: f32,
+    shader_constants: ShaderConstants,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    /// Seconds for a trail to fade to half its brightness
+    #[param(range = 0.05..=5.0, speed = 0.01)]
+    half_life: f32,
+    #[param(label = "Points", range = 0..=8)]
+    num_points: u32,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if !self.half_life.is_finite() || self.half_life <= 0.0 {
+            return Err(format!(
+                "half_life must be positive, got {}",
+                self.half_life
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl crate::controller::Controller for Controller {
+    fn new(size: PhysicalSize<u32>) -> Self {
+        Self {
+            size,
+            cursor: Vec2::ZERO,
+            drawing: false,
+            time: 0.0,
+            shader_constants: ShaderConstants::zeroed(),
+            state: State {
+                half_life: 0.5,
+                num_points: 5,
+            },
+        }
+    }
+
+    fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        if button == MouseButton::Left {
+            self.drawing = state == ElementState::Pressed;
+        }
+    }
+
+    fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor = vec2(position.x as f32, position.y as f32);
+    }
+
+    fn focus_lost(&mut self) {
+        self.drawing = false;
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    fn update(&mut self, time: f32) {
+        // Trails keep their length whatever the frame rate, and stay while paused
+        let dt = (time - self.time).abs();
+        self.time = time;
+        self.shader_constants = ShaderConstants {
+            size: self.size.into(),
+            time,
+            cursor: self.cursor.into(),
+            drawing: self.drawing as u32,
+            decay: 0.5_f32.powf(dt / self.state.half_life),
+            num_points: self.state.num_points,
+        };
+    }
+
+    fn push_constants(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.shader_constants)
+    }
+
+    fn feedback_passes(&self) -> usize {
+        1
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
+    }
+
+    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
+        self.state.params_ui(ui);
+        ui.label("Draw with the left mouse button");
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        Ok(())
+    }
+}
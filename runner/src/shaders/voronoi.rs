@@ -0,0 +1,184 @@
+use crate::{
+    bindings::Binding,
+    controller::ParamsUi,
+    presets::{self, Preset},
+    window::UserEvent,
+};
+use bytemuck::Zeroable;
+use egui::Context;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use shared::{
+    from_pixels,
+    push_constants::voronoi::{ShaderConstants, Style},
+};
+use std::f32::consts::PI;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, MouseButton},
+    event_loop::EventLoopProxy,
+};
+
+/// Every pixel visits every site, so their number is kept reasonable
+const MAX_SITES: usize = 256;
+/// Sites within this many radii of the cursor can be dragged or removed
+const GRAB_RADII: f32 = 2.0;
+
+pub struct Controller {
+    size: PhysicalSize<u32>,
+    cursor: Vec2,
+    hovered: Option<usize>,
+    dragging: bool,
+    shader_constants: ShaderConstants,
+    style: Style,
+    state: State,
+}
+
+#[derive(Serialize, Deserialize, ParamsUi)]
+struct State {
+    sites: Vec<shared::push_constants::Vec2>,
+    #[param(range = 0.0..=0.02, speed = 0.0002)]
+    border_width: f32,
+    #[param(range = 0.0..=0.05, speed = 0.0005)]
+    site_radius: f32,
+    #[param]
+    show_distance: bool,
+}
+
+impl Preset for State {
+    fn validate(&self) -> Result<(), String> {
+        if self.sites.len() > MAX_SITES {
+            return Err(format!(
+                "at most {MAX_SITES} sites are supported, got {}",
+                self.sites.len()
+            ));
+        }
+        if !(self.border_width >= 0.0 && self.site_radius >= 0.0) {
+            return Err("border_width and site_radius must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Sites on a sunflower spiral, which spreads them out evenly
+fn default_sites() -> Vec<shared::push_constants::Vec2> {
+    let golden_angle = PI * (3.0 - 5.0_f32.sqrt());
+    let count = 24;
+    (0..count)
+        .map(|i| {
+            let radius = 0.45 * ((i as f32 + 0.5) / count as f32).sqrt();
+            (radius * Vec2::from_angle(i as f32 * golden_angle)).into()
+        })
+        .collect()
+}
+
+impl Controller {
+    fn site(&self, i: usize) -> Vec2 {
+        self.state.sites[i].into()
+    }
+
+    /// The site nearest to the cursor, if it is close enough to grab
+    fn site_under_cursor(&self) -> Option<usize> {
+        let grab_distance = GRAB_RADII * self.state.site_radius.max(0.005);
+        (0..self.state.sites.len())
+            .map(|i| (i, self.site(i).distance(self.cursor)))
+            .filter(|&(_, distance)| distance < grab_distance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+}
+
+impl crate::controller::Controller for Controller {
+    fn new(size: PhysicalSize<u32>) -> Self {
+        Self {
+            size,
+            cursor: Vec2::ZERO,
+            hovered: None,
+            dragging: false,
+            shader_constants: ShaderConstants::zeroed(),
+            style: Style::zeroed(),
+            state: State {
+                sites: default_sites(),
+                border_width: 0.002,
+                site_radius: 0.006,
+                show_distance: true,
+            },
+        }
+    }
+
+    fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        match (button, state) {
+            (MouseButton::Left, ElementState::Pressed) => {
+                if self.hovered.is_none() && self.state.sites.len() < MAX_SITES {
+                    self.state.sites.push(self.cursor.into());
+                    self.hovered = Some(self.state.sites.len() - 1);
+                }
+                self.dragging = self.hovered.is_some();
+            }
+            (MouseButton::Left, ElementState::Released) => self.dragging = false,
+            (MouseButton::Right, ElementState::Pressed) => {
+                if let Some(i) = self.hovered.take() {
+                    self.state.sites.remove(i);
+                    self.dragging = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        self.cursor = from_pixels(vec2(position.x as f32, position.y as f32), self.size.into());
+        match self.hovered {
+            Some(i) if self.dragging => self.state.sites[i] = self.cursor.into(),
+            _ => self.hovered = self.site_under_cursor(),
+        }
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    fn update(&mut self, _time: f32) {
+        self.shader_constants = ShaderConstants {
+            size: self.size.into(),
+            hovered: self.hovered.map_or(u32::MAX, |i| i as u32),
+        };
+        self.style = Style {
+            border_width: self.state.border_width,
+            site_radius: self.state.site_radius,
+            show_distance: self.state.show_distance as u32,
+        };
+    }
+
+    fn push_constants(&self) -> &[u8] {
+        bytemuck::bytes_of(&self.shader_constants)
+    }
+
+    fn bindings(&self) -> Vec<Binding<'_>> {
+        vec![
+            Binding::uniform(&self.style),
+            Binding::storage(&self.state.sites),
+        ]
+    }
+
+    fn params(&mut self) -> Option<&mut dyn ParamsUi> {
+        Some(&mut self.state)
+    }
+
+    fn ui(&mut self, _ctx: &Context, ui: &mut egui::Ui, _: &EventLoopProxy<UserEvent>) {
+        self.state.params_ui(ui);
+        ui.label(format!("{} sites", self.state.sites.len()));
+        ui.label("Click to add a site, drag to move it and right click to remove it");
+    }
+
+    fn preset(&self) -> Option<String> {
+        presets::to_ron(&self.state)
+    }
+
+    fn load_preset(&mut self, preset: &str) -> Result<(), String> {
+        self.state = presets::from_ron(preset)?;
+        self.hovered = None;
+        self.dragging = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! SPIR-V produced by earlier runs, so launching with unchanged shaders
+//! doesn't wait for rust-gpu.
+//!
+//! Entries are keyed by a SHA-256 hash of everything that goes into a build:
+//! the shader crate, the `shared` crate, the workspace manifest, the rustc
+//! version and toolchain file, the builder settings, and every other source
+//! file the previous build read according to its dep-info, such as path
+//! dependencies outside the repository. Editing any of them produces a new
+//! key, so stale entries are never read. Only the latest [`KEEP`] entries of
+//! each shader are kept.
+
+use crate::{
+    shader::{crate_path, shaders_path},
+    RustGPUShader,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Entries kept per shader, so switching back and forth between a few
+/// versions of a shader doesn't rebuild it
+const KEEP: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct Key {
+    shader: RustGPUShader,
+    hash: u64,
+}
+
+impl Key {
+    /// `sources` are the files the build read, see [`sources`].
+    pub fn new(shader: RustGPUShader, target: &str, sources: &[PathBuf]) -> io::Result<Self> {
+        let mut hasher = KeyHasher(Sha256::new());
+        hasher.write(env!("RUSTC_VERSION").as_bytes());
+        hasher.write(target.as_bytes());
+        hash_dir(&mut hasher, &crate_path(shader))?;
+        hash_dir(&mut hasher, &shaders_path().join("shared"))?;
+        let workspace = shaders_path().join("..");
+        for file in ["Cargo.toml", "Cargo.lock", "rust-toolchain.toml"] {
+            hash_file(&mut hasher, &workspace.join(file))?;
+        }
+        for source in sources {
+            hasher.write(source.to_string_lossy().as_bytes());
+            hash_file(&mut hasher, source)?;
+        }
+        Ok(Self {
+            shader,
+            hash: hasher.finish(),
+        })
+    }
+
+    fn path(self) -> PathBuf {
+        cache_path().join(format!("{}-{:016x}.spv", self.shader, self.hash))
+    }
+}
+
+pub fn load(key: Key) -> Option<Vec<u8>> {
+    fs::read(key.path()).ok()
+}
+
+pub fn store(key: Key, spirv: &[u8]) -> io::Result<()> {
+    let path = key.path();
+    fs::create_dir_all(cache_path())?;
+    write(&path, spirv)?;
+    evict(&cache_path(), key.shader)
+}
+
+/// Source files read by the latest build of `shader`, empty if unknown.
+pub fn sources(shader: RustGPUShader) -> Vec<PathBuf> {
+    fs::read_to_string(sources_path(shader))
+        .unwrap_or_default()
+        .lines()
+        .map(PathBuf::from)
+        .collect()
+}
+
+pub fn store_sources(shader: RustGPUShader, sources: &[PathBuf]) -> io::Result<()> {
+    fs::create_dir_all(cache_path())?;
+    let lines = sources
+        .iter()
+        .map(|source| format!("{}\n", source.display()))
+        .collect::<String>();
+    write(&sources_path(shader), lines.as_bytes())
+}
+
+fn sources_path(shader: RustGPUShader) -> PathBuf {
+    cache_path().join(format!("{shader}.sources"))
+}
+
+/// Writes then renames, so a runner killed mid-write can't leave a truncated file
+fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(temp, path)
+}
+
+/// Removes all but the [`KEEP`] most recently stored entries of `shader`, and
+/// entries named by earlier versions of the cache, which lack the shader name.
+fn evict(dir: &Path, shader: RustGPUShader) -> io::Result<()> {
+    let prefix = format!("{shader}-");
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str().and_then(|name| name.strip_suffix(".spv")) else {
+            continue;
+        };
+        if !name.contains('-') {
+            fs::remove_file(entry.path())?;
+        } else if name.starts_with(&prefix) {
+            entries.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    entries.sort_by(|a, b| b.cmp(a));
+    for (_, path) in entries.into_iter().skip(KEEP) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn cache_path() -> PathBuf {
+    PathBuf::from(env!("OUT_DIR")).join("spirv-cache")
+}
+
+/// Hashes the inputs of a key. Unlike `DefaultHasher` and the `Hash` impls
+/// of std, the result doesn't change between Rust releases.
+struct KeyHasher(Sha256);
+
+impl KeyHasher {
+    /// Prefixed with the length, so consecutive inputs can't run together
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update((bytes.len() as u64).to_le_bytes());
+        self.0.update(bytes);
+    }
+
+    fn finish(self) -> u64 {
+        let digest = self.0.finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+fn hash_dir(hasher: &mut KeyHasher, dir: &Path) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    // `read_dir` order is platform dependent
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if entry.file_type()?.is_dir() {
+            if name != "target" {
+                hasher.write(name.to_string_lossy().as_bytes());
+                hash_dir(hasher, &entry.path())?;
+            }
+        } else {
+            hasher.write(name.to_string_lossy().as_bytes());
+            hasher.write(&fs::read(entry.path())?);
+        }
+    }
+    Ok(())
+}
+
+fn hash_file(hasher: &mut KeyHasher, path: &Path) -> io::Result<()> {
+    match fs::read(path) {
+        Ok(contents) => {
+            hasher.write(b"file");
+            hasher.write(&contents);
+        }
+        // Cargo.lock isn't committed
+        Err(err) if err.kind() == io::ErrorKind::NotFound => hasher.write(b"missing"),
+        Err(err) => return Err(err),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_hasher_is_stable() {
+        // Changing this breaks every cached entry, so it should only change
+        // along with the cache's layout
+        let mut hasher = KeyHasher(Sha256::new());
+        hasher.write(b"spirv-unknown-vulkan1.1");
+        assert_eq!(hasher.finish(), 0x208e27bed84cb2be);
+    }
+
+    #[test]
+    fn test_evict() {
+        let dir = std::env::temp_dir().join(format!("spirv-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let names = (0..KEEP + 2)
+            .map(|i| format!("SphericalHarmonics-{i:016x}.spv"))
+            .chain([
+                "SphericalHarmonicsShape-0000000000000000.spv".to_string(),
+                "SphericalHarmonics.sources".to_string(),
+                // Named by an earlier version of the cache
+                "00000000000000ff.spv".to_string(),
+            ]);
+        for name in names {
+            fs::write(dir.join(name), []).unwrap();
+        }
+
+        evict(&dir, RustGPUShader::SphericalHarmonics).unwrap();
+        let mut remaining = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        remaining.sort();
+
+        let kept = remaining
+            .iter()
+            .filter(|name| name.starts_with("SphericalHarmonics-"))
+            .count();
+        assert_eq!(kept, KEEP);
+        assert!(remaining.contains(&"SphericalHarmonicsShape-0000000000000000.spv".to_string()));
+        assert!(remaining.contains(&"SphericalHarmonics.sources".to_string()));
+        assert!(!remaining.contains(&"00000000000000ff.spv".to_string()));
+    }
+}
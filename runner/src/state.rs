@@ -1,18 +1,29 @@
 use crate::{
+    blit::{Tonemapping, Upscaling},
+    clock::Clock,
     context::GraphicsContext,
     controller::{new_controller, Controller},
+    gestures::{Gesture, Touches},
     render_pass::RenderPass,
-    shader::{self, CompiledShaderModules},
-    texture::Texture,
+    shader::CompiledShaderModules,
     ui::{Ui, UiState},
     window::Window,
     Options, RustGPUShader,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{compiler::ShaderCompiler, diagnostics::Diagnostic};
 
+use glam::{vec2, Vec2};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, Touch,
+        WindowEvent,
+    },
+    event_loop::ControlFlow,
 };
 
 pub struct State {
@@ -21,61 +32,122 @@ pub struct State {
     controllers: Vec<Box<dyn Controller>>,
     ui: Ui,
     ui_state: UiState,
-    depth_texture: Texture,
+    clock: Clock,
+    modifiers: ModifiersState,
+    touches: Touches,
+    #[cfg(not(target_arch = "wasm32"))]
+    compiler: ShaderCompiler,
+    /// Latest successful build of each shader, indexed by `RustGPUShader`
+    modules: Vec<Option<CompiledShaderModules>>,
+    /// Used again when the device is recreated
+    options: Options,
+    /// When to try recreating the device again, after failing to
+    #[cfg(not(target_arch = "wasm32"))]
+    retry_at: Option<Instant>,
 }
 
+/// Time between attempts to recreate a lost device
+#[cfg(not(target_arch = "wasm32"))]
+const RECOVERY_DELAY: Duration = Duration::from_secs(1);
+
 impl State {
-    pub async fn new(
-        window: &Window,
-        compiled_shader_modules: CompiledShaderModules,
-        options: Options,
-    ) -> Self {
-        let ctx = GraphicsContext::new(&window.window, &options).await;
+    pub async fn new(window: &Window, options: Options) -> Self {
+        let ctx = GraphicsContext::new(&window.window, &options)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
 
         let ui = Ui::new(window);
 
-        let ui_state = UiState::new(options.shader);
+        let mut ui_state = UiState::new(options.shader);
 
         let controllers = RustGPUShader::iter()
             .map(|s| new_controller(s, window.window.inner_size()))
             .collect::<Vec<Box<dyn Controller>>>();
 
-        let controller = &controllers[ui_state.active_shader as usize];
-
-        let rpass = RenderPass::new(
-            &ctx,
-            compiled_shader_modules,
-            options,
-            controller.buffers(),
-        );
+        let rpass = RenderPass::new(&ctx, options.clone());
+        ui_state.msaa = rpass.sample_count();
+        ui_state.sample_counts = ctx.sample_counts.clone();
+        ui_state.present_mode = ctx.config.present_mode;
+        ui_state.present_modes = ctx.present_modes.clone();
+        ui_state.adapter_report = ctx.adapter_report();
+        ui_state.supersampling = rpass.supersampling();
+        ui_state.render_scale = rpass.render_scale();
+        ui_state.upscaling = rpass.upscaling();
+        ui_state.tonemapping = rpass.tonemapping();
+        ui_state.accumulate = rpass.accumulate();
+        ui_state.stats_window = options.stats_window;
 
-        let depth_texture =
-            Texture::create_depth_texture(&ctx.device, &ctx.config, "depth_texture");
+        #[cfg(not(target_arch = "wasm32"))]
+        let compiler = ShaderCompiler::new(&options, window.event_loop.create_proxy());
+        #[cfg(not(target_arch = "wasm32"))]
+        let modules = RustGPUShader::iter().map(|_| None).collect();
+        #[cfg(target_arch = "wasm32")]
+        let modules: Vec<_> = RustGPUShader::iter().map(crate::shader::embedded).collect();
+        #[cfg(target_arch = "wasm32")]
+        {
+            ui_state.compiled = RustGPUShader::iter()
+                .filter(|&shader| modules[shader as usize].is_some())
+                .collect();
+        }
 
-        Self {
+        let mut state = Self {
             rpass,
             controllers,
             ctx,
             ui,
             ui_state,
-            depth_texture,
-        }
+            clock: Clock::new(),
+            modifiers: ModifiersState::empty(),
+            touches: Touches::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            compiler,
+            modules,
+            options,
+            #[cfg(not(target_arch = "wasm32"))]
+            retry_at: None,
+        };
+        state.resize_controller();
+        // On the web every shader is already built
+        #[cfg(target_arch = "wasm32")]
+        state.rebuild_pipeline();
+        state
     }
 
     fn controller(&mut self) -> &mut dyn Controller {
         &mut *self.controllers[self.ui_state.active_shader as usize]
     }
 
+    /// Size the active controller renders at, which differs from the
+    /// window's with a render scale or supersampling
+    fn render_size(&self) -> PhysicalSize<u32> {
+        let controller = &*self.controllers[self.ui_state.active_shader as usize];
+        self.rpass.render_size(&self.ctx, controller)
+    }
+
+    /// Controller pixels per window pixel along each axis
+    fn scale(&self) -> Vec2 {
+        let size = self.render_size();
+        vec2(
+            size.width as f32 / self.ctx.config.width as f32,
+            size.height as f32 / self.ctx.config.height as f32,
+        )
+    }
+
+    fn resize_controller(&mut self) {
+        let size = self.render_size();
+        self.controller().resize(size);
+    }
+
+    pub fn clock(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         if size.width != 0 && size.height != 0 {
             self.ctx.config.width = size.width;
             self.ctx.config.height = size.height;
-            self.ctx
-                .surface
-                .configure(&self.ctx.device, &self.ctx.config);
-            self.controller().resize(size);
-            self.depth_texture =
-                Texture::create_depth_texture(&self.ctx.device, &self.ctx.config, "depth_texture");
+            self.ctx.configure_surface();
+            self.resize_controller();
         }
     }
 
@@ -84,6 +156,7 @@ impl State {
     }
 
     pub fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        let position = scale_position(position, self.scale());
         self.controller().mouse_move(position);
     }
 
@@ -91,41 +164,223 @@ impl State {
         self.controller().mouse_scroll(delta);
     }
 
+    pub fn touch(&mut self, touch: Touch) {
+        let Some(gesture) = self.touches.touch(touch) else {
+            return;
+        };
+        let render_scale = self.scale();
+        let controller = self.controller();
+        match gesture {
+            Gesture::Press(position) => {
+                controller.mouse_move(scale_position(position, render_scale));
+                controller.mouse_input(ElementState::Pressed, MouseButton::Left);
+            }
+            Gesture::Move(position) => {
+                controller.mouse_move(scale_position(position, render_scale))
+            }
+            Gesture::Release => controller.mouse_input(ElementState::Released, MouseButton::Left),
+            Gesture::TwoFinger {
+                pan,
+                scale,
+                rotation,
+            } => {
+                controller.pan(pan * render_scale);
+                controller.pinch(scale);
+                controller.rotate(rotation);
+            }
+        }
+    }
+
+    pub fn touchpad_magnify(&mut self, delta: f64) {
+        self.controller().pinch(1.0 + delta as f32);
+    }
+
+    pub fn touchpad_rotate(&mut self, delta: f32) {
+        self.controller().rotate(delta.to_radians());
+    }
+
+    pub fn keyboard_input(&mut self, input: KeyboardInput) {
+        self.controller().keyboard_input(input);
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+        self.controller().modifiers_changed(modifiers);
+    }
+
+    pub fn focus_lost(&mut self) {
+        self.controller().focus_lost();
+    }
+
     pub fn update(&mut self) {
-        self.controller().update();
+        let time = self.clock.tick();
+        self.ui_state.time = time;
+        self.ui_state.paused = self.clock.paused();
+        self.ui_state.speed = self.clock.speed();
+        self.controller().update(time);
     }
 
     pub fn render(&mut self, window: &winit::window::Window) -> Result<(), wgpu::SurfaceError> {
+        let controller = &*self.controllers[self.ui_state.active_shader as usize];
+        if self.rpass.layout_changed(controller) && !self.rebuild_pipeline() {
+            self.rpass.clear_pipeline();
+        }
         let controller = &mut *self.controllers[self.ui_state.active_shader as usize];
-        let depth_texture = controller.buffers().map(|_| &self.depth_texture);
 
+        self.rpass.prepare_accumulation(controller);
         self.rpass.render(
             &self.ctx,
             window,
             &mut self.ui,
             &mut self.ui_state,
             controller,
-            depth_texture,
         )
     }
 
-    pub fn update_and_render(
-        &mut self,
-        window: &winit::window::Window,
-    ) -> Result<(), wgpu::SurfaceError> {
+    /// Skips rendering while the window is minimized, and recreates the
+    /// device when it is lost or runs out of memory.
+    pub fn update_and_render(&mut self, window: &winit::window::Window) {
         self.update();
-        self.render(&window)
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        if self.ctx.failure().is_none() {
+            if let Err(err) = self.render(window) {
+                self.ctx.fail(err.to_string());
+            }
+        }
+        if let Some(failure) = self.ctx.failure() {
+            self.recover(window, &failure);
+        }
+    }
+
+    /// How the event loop should wait instead of rendering while the device
+    /// is lost: until the next attempt to recreate it, or for good on the web.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn recovery_wait(&self) -> Option<ControlFlow> {
+        self.retry_at.map(ControlFlow::WaitUntil)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn recovery_wait(&self) -> Option<ControlFlow> {
+        self.ctx.failure().map(|_| ControlFlow::Wait)
+    }
+
+    /// Replaces the device and everything created on it. Controllers keep
+    /// their state, so the frame continues where it stopped. Failed attempts
+    /// are retried after `RECOVERY_DELAY`, see [`State::recovery_wait`].
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recover(&mut self, window: &winit::window::Window, failure: &str) {
+        if self
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() < retry_at)
+        {
+            return;
+        }
+        eprintln!("Recreating the device after: {failure}");
+        // Only one surface can present to the window at a time
+        self.ctx.surface = None;
+        let options = Options {
+            present_mode: self.ui_state.present_mode,
+            ..self.options.clone()
+        };
+        let ctx = match futures::executor::block_on(GraphicsContext::new(window, &options)) {
+            Ok(ctx) => ctx,
+            Err(err) => {
+                eprintln!("Failed to recreate the device: {err}");
+                self.retry_at = Some(Instant::now() + RECOVERY_DELAY);
+                return;
+            }
+        };
+        self.retry_at = None;
+        self.ctx = ctx;
+        self.rpass.new_device(&self.ctx);
+        self.ui.new_device();
+        self.ui_state.msaa = self.rpass.sample_count();
+        self.ui_state.sample_counts = self.ctx.sample_counts.clone();
+        self.ui_state.present_mode = self.ctx.config.present_mode;
+        self.ui_state.present_modes = self.ctx.present_modes.clone();
+        self.ui_state.adapter_report = self.ctx.adapter_report();
+        self.resize_controller();
+        if !self.rebuild_pipeline() {
+            self.rpass.clear_pipeline();
+        }
+    }
+
+    /// The device can't be recreated without blocking the browser's event
+    /// loop, so the page shows why rendering stopped until it is reloaded.
+    #[cfg(target_arch = "wasm32")]
+    fn recover(&mut self, _window: &winit::window::Window, failure: &str) {
+        const ID: &str = "device-lost";
+        let Some(document) = web_sys::window().and_then(|win| win.document()) else {
+            return;
+        };
+        if document.get_element_by_id(ID).is_some() {
+            return;
+        }
+        let Ok(message) = document.create_element("p") else {
+            return;
+        };
+        message.set_id(ID);
+        message.set_text_content(Some(&format!(
+            "The GPU device failed ({failure}), reload the page"
+        )));
+        if let Some(body) = document.body() {
+            let _ = body.append_child(&message);
+        }
     }
 
     pub fn ui_consumes_event(&mut self, event: &WindowEvent) -> bool {
         self.ui.consumes_event(event)
     }
 
+    pub fn compile_started(&mut self, shader: RustGPUShader) {
+        self.ui_state.compiling = Some(shader);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compile_failed(&mut self, shader: RustGPUShader, diagnostics: Vec<Diagnostic>) {
+        self.ui_state.compiling = None;
+        self.ui_state.compile_errors[shader as usize] = diagnostics;
+    }
+
+    pub fn watch_failed(&mut self, err: String) {
+        self.ui_state.watch_error = Some(err);
+    }
+
     pub fn new_module(&mut self, shader: RustGPUShader, new_module: CompiledShaderModules) {
-        let controller = &self.controllers[shader as usize];
-        let buffers = controller.buffers();
-        self.ui_state.active_shader = shader;
-        self.rpass.new_module(&self.ctx, new_module, buffers);
+        self.ui_state.compiling = None;
+        if !self.ui_state.compiled.contains(&shader) {
+            self.ui_state.compiled.push(shader);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.ui_state.compile_errors[shader as usize].clear();
+        self.modules[shader as usize] = Some(new_module);
+        if shader == self.ui_state.active_shader {
+            // On failure the previous pipeline keeps rendering
+            self.rebuild_pipeline();
+        }
+    }
+
+    /// Returns whether the active shader's pipeline was created.
+    fn rebuild_pipeline(&mut self) -> bool {
+        let shader = self.ui_state.active_shader;
+        let Some(modules) = &self.modules[shader as usize] else {
+            return false;
+        };
+        let controller = &*self.controllers[shader as usize];
+        match self.rpass.new_module(&self.ctx, modules, controller) {
+            Ok(()) => true,
+            #[cfg(not(target_arch = "wasm32"))]
+            Err(err) => {
+                self.ui_state.compile_errors[shader as usize] = vec![Diagnostic::new(err)];
+                false
+            }
+            // There is no error overlay on the web, so it goes to the console
+            #[cfg(target_arch = "wasm32")]
+            Err(err) => panic!("{err}"),
+        }
     }
 
     pub fn new_vertices(&mut self) {
@@ -133,20 +388,69 @@ impl State {
         self.rpass.new_vertices(&self.ctx, controller.buffers());
     }
 
+    pub fn new_textures(&mut self) {
+        let controller = &self.controllers[self.ui_state.active_shader as usize];
+        self.rpass.new_textures(&self.ctx, &controller.textures());
+    }
+
     pub fn switch_shader(&mut self, shader: RustGPUShader) {
-        self.new_module(
-            shader,
-            shader::maybe_watch(
-                &Options {
-                    force_spirv_passthru: false,
-                    shader,
-                },
-                None,
-            ),
-        )
+        // Keys released from now on go to the new controller
+        self.controller().focus_lost();
+        self.ui_state.active_shader = shader;
+        // Modifiers may have changed while another controller was active
+        let modifiers = self.modifiers;
+        self.controller().modifiers_changed(modifiers);
+        // The window may have been resized, or the scale changed, since
+        // this controller was last active
+        self.resize_controller();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.compiler.select(shader);
+        if !self.rebuild_pipeline() {
+            self.rpass.clear_pipeline();
+        }
     }
 
-    pub fn set_vsync(&mut self, enable: bool) {
-        self.ctx.set_vsync(enable);
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        self.ui_state.present_mode = mode;
+        self.ctx.set_present_mode(mode);
+        self.ui_state.adapter_report = self.ctx.adapter_report();
     }
+
+    pub fn set_msaa(&mut self, sample_count: u32) {
+        self.ui_state.msaa = sample_count;
+        self.rpass.set_sample_count(sample_count);
+        self.rebuild_pipeline();
+    }
+
+    pub fn set_supersampling(&mut self, factor: u32) {
+        self.ui_state.supersampling = factor;
+        self.rpass.set_supersampling(factor);
+        self.resize_controller();
+    }
+
+    pub fn set_render_scale(&mut self, percent: u32) {
+        self.ui_state.render_scale = percent;
+        self.rpass.set_render_scale(percent);
+        self.resize_controller();
+    }
+
+    pub fn set_upscaling(&mut self, upscaling: Upscaling) {
+        self.ui_state.upscaling = upscaling;
+        self.rpass.set_upscaling(upscaling);
+    }
+
+    pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+        self.ui_state.tonemapping = tonemapping;
+        self.rpass.set_tonemapping(tonemapping);
+    }
+
+    pub fn set_accumulate(&mut self, accumulate: bool) {
+        self.ui_state.accumulate = accumulate;
+        self.rpass.set_accumulate(accumulate);
+    }
+}
+
+/// Maps a position in the window to the pixels the controller renders
+fn scale_position(position: PhysicalPosition<f64>, scale: Vec2) -> PhysicalPosition<f64> {
+    PhysicalPosition::new(position.x * scale.x as f64, position.y * scale.y as f64)
 }
@@ -0,0 +1,141 @@
+//! Textures the shader renders into before the output. Shaders write linear
+//! HDR values into the `hdr` texture, which is tonemapped into the output.
+//! Meshes render into a multisampled texture that resolves into `hdr`, and
+//! supersampled fullscreen shaders into an enlarged texture that is averaged
+//! down into it. When accumulating, `hdr` is averaged into `accumulated`,
+//! which is tonemapped instead.
+
+use crate::{blit::Blit, context::GraphicsContext, texture::Texture};
+
+/// Format of the textures shaders render into, which holds values above 1
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TargetSettings {
+    /// Size of `hdr`, which differs from the output's with a render scale
+    pub width: u32,
+    pub height: u32,
+    /// Filter used to scale `hdr` to the output
+    pub filter: wgpu::FilterMode,
+    /// Whether the shader draws a mesh, which needs a depth buffer
+    pub depth: bool,
+    /// MSAA samples per pixel, 1 renders straight into `hdr`
+    pub sample_count: u32,
+    /// Pixels rendered per `hdr` pixel along each axis
+    pub supersampling: u32,
+    pub accumulate: bool,
+}
+
+#[derive(Default)]
+pub struct Targets {
+    settings: Option<TargetSettings>,
+    /// The texture that is tonemapped, and its bind group for [`Blit::tonemap`].
+    /// `None` before the first [`Targets::prepare`].
+    pub hdr: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+    pub multisampled: Option<wgpu::TextureView>,
+    pub depth: Option<Texture>,
+    /// The enlarged texture, and its bind group for [`Blit::downsample`]
+    pub supersampled: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+    /// The average of the frames since the view last changed, and its bind
+    /// group for [`Blit::tonemap`]
+    pub accumulated: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl Targets {
+    /// Recreates the textures if `settings` changed since the last call,
+    /// and returns whether it did.
+    pub fn prepare(
+        &mut self,
+        ctx: &GraphicsContext,
+        blit: &Blit,
+        settings: TargetSettings,
+    ) -> bool {
+        if self.settings == Some(settings) {
+            return false;
+        }
+        self.settings = Some(settings);
+        let TargetSettings {
+            width,
+            height,
+            filter,
+            depth,
+            sample_count,
+            supersampling,
+            accumulate,
+        } = settings;
+        let hdr = create_view(
+            ctx,
+            "HDR Texture",
+            width,
+            height,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let bind_group = blit.bind_group(&ctx.device, &hdr, filter);
+        self.hdr = Some((hdr, bind_group));
+        self.multisampled = (sample_count > 1).then(|| {
+            create_view(
+                ctx,
+                "Multisampled Texture",
+                width,
+                height,
+                sample_count,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+        self.depth = depth.then(|| {
+            Texture::create_depth_texture(&ctx.device, width, height, sample_count, "depth_texture")
+        });
+        self.supersampled = (supersampling > 1).then(|| {
+            let view = create_view(
+                ctx,
+                "Supersampled Texture",
+                width * supersampling,
+                height * supersampling,
+                1,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+            let bind_group = blit.bind_group(&ctx.device, &view, wgpu::FilterMode::Nearest);
+            (view, bind_group)
+        });
+        self.accumulated = accumulate.then(|| {
+            let view = create_view(
+                ctx,
+                "Accumulated Texture",
+                width,
+                height,
+                1,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+            let bind_group = blit.bind_group(&ctx.device, &view, filter);
+            (view, bind_group)
+        });
+        true
+    }
+}
+
+fn create_view(
+    ctx: &GraphicsContext,
+    label: &str,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::TextureView {
+    ctx.device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
@@ -1,35 +1,120 @@
 use egui::{
     epaint::{textures::TexturesDelta, ClippedPrimitive},
-    vec2, Align2, Context, CursorIcon, Layout, Vec2,
+    vec2, Align2, Color32, Context, CursorIcon, Layout, Vec2,
 };
 use strum::IntoEnumIterator;
 use winit::{event::WindowEvent, event_loop::EventLoopProxy};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::diagnostics::Diagnostic;
 use crate::{
+    blit::{Tonemapper, Tonemapping, Upscaling},
     controller::Controller,
-    fps_counter::FpsCounter,
+    fps_counter::{FpsCounter, DEFAULT_WINDOW},
+    presets,
+    profiler::PassTimings,
     window::{UserEvent, Window},
     RustGPUShader,
 };
 
+/// Colours of the passes in the fps window
+const SHADER_COLOR: Color32 = Color32::from_rgb(90, 170, 255);
+const UI_COLOR: Color32 = Color32::from_rgb(255, 180, 60);
+const PRESENT_COLOR: Color32 = Color32::from_rgb(120, 220, 120);
+/// Frames shown in the frame-time graph
+const GRAPH_FRAMES: usize = 240;
+
 pub struct UiState {
-    pub fps: usize,
+    /// Timings of a recent frame, see [`crate::profiler`]
+    pub pass_timings: Option<PassTimings>,
     pub show_fps: bool,
-    pub vsync: bool,
+    /// Frames the frame-time statistics cover
+    pub stats_window: usize,
+    /// Path of the latest CSV export, or why it failed
+    pub export_result: Option<Result<std::path::PathBuf, String>>,
+    pub present_mode: wgpu::PresentMode,
+    pub present_modes: Vec<wgpu::PresentMode>,
+    /// See [`crate::context::GraphicsContext::adapter_report`]
+    pub adapter_report: String,
+    /// MSAA samples of mesh shaders, one of `sample_counts`
+    pub msaa: u32,
+    pub sample_counts: Vec<u32>,
+    /// Supersampling factor of fullscreen shaders
+    pub supersampling: u32,
+    /// Resolution of fullscreen shaders in percent of the window
+    pub render_scale: u32,
+    pub upscaling: Upscaling,
+    pub tonemapping: Tonemapping,
+    /// Whether fullscreen shaders average their frames while the view is static
+    pub accumulate: bool,
+    pub accumulated_frames: u32,
+    pub time: f32,
+    pub paused: bool,
+    pub speed: f32,
     pub active_shader: RustGPUShader,
+    pub compiling: Option<RustGPUShader>,
+    pub compiled: Vec<RustGPUShader>,
+    /// Errors from the latest build of each shader, indexed by `RustGPUShader`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub compile_errors: Vec<Vec<Diagnostic>>,
+    /// Why changes to the shader sources may not trigger a rebuild
+    pub watch_error: Option<String>,
     pub cursor_icon: CursorIcon,
+    pub preset_name: String,
+    pub preset_error: Option<String>,
+    /// Preset names of `presets_shader`, refreshed when the shader changes
+    pub presets: Vec<String>,
+    pub presets_shader: Option<RustGPUShader>,
 }
 
 impl UiState {
     pub fn new(active_shader: RustGPUShader) -> Self {
         Self {
-            fps: 0,
+            pass_timings: None,
             show_fps: true,
-            vsync: true,
+            stats_window: DEFAULT_WINDOW,
+            export_result: None,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            present_modes: vec![],
+            adapter_report: String::new(),
+            msaa: 1,
+            sample_counts: vec![1],
+            supersampling: 1,
+            render_scale: 100,
+            upscaling: Upscaling::Bilinear,
+            tonemapping: Tonemapping {
+                tonemapper: Tonemapper::None,
+                exposure: 0.0,
+            },
+            accumulate: false,
+            accumulated_frames: 0,
+            time: 0.0,
+            paused: false,
+            speed: 1.0,
             active_shader,
+            compiling: None,
+            compiled: vec![],
+            #[cfg(not(target_arch = "wasm32"))]
+            compile_errors: RustGPUShader::iter().map(|_| vec![]).collect(),
+            watch_error: None,
             cursor_icon: CursorIcon::default(),
+            preset_name: String::new(),
+            preset_error: None,
+            presets: vec![],
+            presets_shader: None,
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn failed_to_compile(&self, shader: RustGPUShader) -> bool {
+        !self.compile_errors[shader as usize].is_empty()
+    }
+
+    /// Shaders are built ahead of time for the web, see [`crate::shader::embedded`]
+    #[cfg(target_arch = "wasm32")]
+    fn failed_to_compile(&self, _shader: RustGPUShader) -> bool {
+        false
+    }
 }
 
 pub struct Ui {
@@ -53,6 +138,12 @@ impl Ui {
         }
     }
 
+    /// Starts a new egui context, whose textures are uploaded again to the
+    /// renderer of a recreated device. The state of egui's windows is lost.
+    pub fn new_device(&mut self) {
+        self.context = Context::default();
+    }
+
     pub fn consumes_event(&mut self, event: &WindowEvent) -> bool {
         self.egui_winit_state
             .on_event(&self.context, &event)
@@ -65,7 +156,9 @@ impl Ui {
         ui_state: &mut UiState,
         controller: &mut dyn Controller,
     ) -> (Vec<ClippedPrimitive>, TexturesDelta) {
-        ui_state.fps = self.fps_counter.tick();
+        self.fps_counter.set_window(ui_state.stats_window);
+        self.fps_counter
+            .tick(ui_state.active_shader, ui_state.pass_timings);
         let raw_input = self.egui_winit_state.take_egui_input(&window);
         let full_output = self.context.run(raw_input, |ctx| {
             self.ui(ctx, ui_state, controller);
@@ -79,6 +172,31 @@ impl Ui {
         (clipped_primitives, full_output.textures_delta)
     }
 
+    /// Shows the errors of the active shader's latest build, if it failed
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compile_errors_ui(ctx: &Context, ui_state: &UiState, window_margin: f32) {
+        let compile_errors = &ui_state.compile_errors[ui_state.active_shader as usize];
+        if compile_errors.is_empty() {
+            return;
+        }
+        egui::Window::new(format!("{} failed to compile", ui_state.active_shader))
+            .anchor(Align2::CENTER_TOP, vec2(0.0, window_margin))
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for diagnostic in compile_errors {
+                            if let Some(location) = &diagnostic.location {
+                                ui.label(egui::RichText::new(location).strong());
+                            }
+                            ui.label(egui::RichText::new(&diagnostic.rendered).monospace());
+                            ui.separator();
+                        }
+                    });
+            });
+    }
+
     fn send_event(&self, event: UserEvent) {
         let _ = self.event_proxy.send_event(event);
     }
@@ -92,8 +210,14 @@ impl Ui {
             .show(ctx, |ui| {
                 ui.with_layout(Layout::default(), |ui| {
                     for shader in RustGPUShader::iter() {
+                        let mut label = egui::RichText::new(shader.to_string());
+                        if ui_state.failed_to_compile(shader) {
+                            label = label.color(Color32::RED);
+                        } else if !ui_state.compiled.contains(&shader) {
+                            label = label.weak();
+                        }
                         if ui
-                            .selectable_label(ui_state.active_shader == shader, shader.to_string())
+                            .selectable_label(ui_state.active_shader == shader, label)
                             .clicked()
                         {
                             if ui_state.active_shader != shader {
@@ -102,11 +226,34 @@ impl Ui {
                         }
                     }
                 });
+                if let Some(shader) = ui_state.compiling {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Compiling {shader} ({}/{})",
+                            ui_state.compiled.len(),
+                            RustGPUShader::iter().len()
+                        ));
+                    });
+                }
+                if let Some(err) = &ui_state.watch_error {
+                    ui.separator();
+                    ui.colored_label(Color32::YELLOW, "Not reloading on changes")
+                        .on_hover_text(err);
+                }
                 ui.separator();
                 ui.checkbox(&mut ui_state.show_fps, "fps counter");
-                if ui.checkbox(&mut ui_state.vsync, "V-Sync").clicked() {
-                    self.send_event(UserEvent::SetVSync(ui_state.vsync));
+                self.present_mode_ui(ui, ui_state);
+                self.anti_aliasing_ui(ui, ui_state, controller);
+                if controller.buffers().is_none() {
+                    self.render_scale_ui(ui, ui_state);
+                    self.accumulation_ui(ui, ui_state);
                 }
+                self.tonemapping_ui(ui, ui_state);
+                ui.separator();
+                ui.collapsing("Presets", |ui| self.presets_ui(ui, ui_state, controller));
+                ui.collapsing("Adapter", |ui| self.adapter_ui(ui, ui_state));
             });
         if controller.has_ui() {
             egui::Window::new(ui_state.active_shader.to_string())
@@ -116,15 +263,359 @@ impl Ui {
                     controller.ui(ctx, ui, &self.event_proxy);
                 });
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        Self::compile_errors_ui(ctx, ui_state, window_margin);
+        egui::Window::new("Timeline")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(Align2::LEFT_BOTTOM, window_margin * vec2(1.0, -1.0))
+            .show(ctx, |ui| self.timeline_ui(ui, ui_state));
         if ui_state.show_fps {
             egui::Window::new("fps")
                 .title_bar(false)
                 .resizable(false)
-                .interactable(false)
                 .anchor(Align2::RIGHT_BOTTOM, Vec2::splat(-window_margin))
                 .show(ctx, |ui| {
-                    ui.label(format!("FPS: {}", ui_state.fps));
+                    ui.label(format!("FPS: {}", self.fps_counter.fps()));
+                    if let Some(timings) = ui_state.pass_timings {
+                        let source = if timings.gpu { "GPU" } else { "CPU" };
+                        for (label, duration, color) in [
+                            ("Shader", timings.shader, SHADER_COLOR),
+                            ("UI", timings.ui, UI_COLOR),
+                            ("Present", timings.present, PRESENT_COLOR),
+                        ] {
+                            ui.colored_label(
+                                color,
+                                format!("{label}: {:.2} ms", duration.as_secs_f32() * 1000.0),
+                            );
+                        }
+                        ui.weak(format!("Passes timed on the {source}"));
+                    }
+                    self.frame_time_graph(ui);
+                    ui.separator();
+                    self.frame_stats_ui(ui, ui_state);
+                });
+        }
+    }
+
+    /// A bar per frame of the latest `GRAPH_FRAMES` frames, stacking the pass
+    /// timings over the whole frame time, with a line every 60 Hz frame
+    fn frame_time_graph(&self, ui: &mut egui::Ui) {
+        const FRAME_MS: f32 = 1000.0 / 60.0;
+        let history = self.fps_counter.history().iter().rev().take(GRAPH_FRAMES);
+        let (response, painter) = ui.allocate_painter(vec2(240.0, 60.0), egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(96));
+        let ms = |duration: std::time::Duration| duration.as_secs_f32() * 1000.0;
+        // Whole 60 Hz frames, so that the scale only changes on big spikes
+        let longest = history
+            .clone()
+            .map(|time| ms(time.frame))
+            .fold(0.0, f32::max);
+        let scale_ms = (longest / FRAME_MS).ceil().max(1.0) * FRAME_MS;
+        let bar_width = rect.width() / GRAPH_FRAMES as f32;
+        for (age, time) in history.enumerate() {
+            let x = rect.right() - (age + 1) as f32 * bar_width;
+            let mut bottom = rect.bottom();
+            let mut bar = |ms: f32, color| {
+                let top = bottom - ms / scale_ms * rect.height();
+                painter.rect_filled(
+                    egui::Rect::from_min_max(egui::pos2(x, top), egui::pos2(x + bar_width, bottom)),
+                    0.0,
+                    color,
+                );
+                bottom = top;
+            };
+            let mut rest = ms(time.frame);
+            if let Some(passes) = time.passes {
+                for (duration, color) in [
+                    (passes.shader, SHADER_COLOR),
+                    (passes.ui, UI_COLOR),
+                    (passes.present, PRESENT_COLOR),
+                ] {
+                    bar(ms(duration), color);
+                    rest -= ms(duration);
+                }
+            }
+            bar(rest.max(0.0), Color32::GRAY);
+        }
+        for frame in 1..(scale_ms / FRAME_MS).round() as u32 {
+            let y = rect.bottom() - frame as f32 * FRAME_MS / scale_ms * rect.height();
+            painter.hline(rect.x_range(), y, (1.0, Color32::from_white_alpha(64)));
+        }
+    }
+
+    /// Statistics and a histogram of the frame times in the window, and the
+    /// button that exports them
+    fn frame_stats_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        const BINS: usize = 40;
+        ui.horizontal(|ui| {
+            ui.label("Window:");
+            ui.add(
+                egui::DragValue::new(&mut ui_state.stats_window)
+                    .clamp_range(10..=100_000)
+                    .suffix(" frames"),
+            );
+        });
+        if let Some(stats) = self.fps_counter.stats() {
+            ui.label(format!(
+                "min {:.2}  mean {:.2}  max {:.2} ms",
+                stats.min, stats.mean, stats.max
+            ));
+            ui.label(format!("p95 {:.2}  p99 {:.2} ms", stats.p95, stats.p99));
+            let counts = self.fps_counter.histogram(stats, BINS);
+            let (response, painter) = ui.allocate_painter(vec2(240.0, 40.0), egui::Sense::hover());
+            let rect = response.rect;
+            painter.rect_filled(rect, 0.0, Color32::from_black_alpha(96));
+            let highest = counts.iter().copied().max().unwrap_or(1).max(1);
+            let bar_width = rect.width() / BINS as f32;
+            for (bin, &count) in counts.iter().enumerate() {
+                let x = rect.left() + bin as f32 * bar_width;
+                let top = rect.bottom() - count as f32 / highest as f32 * rect.height();
+                painter.rect_filled(
+                    egui::Rect::from_min_max(
+                        egui::pos2(x, top),
+                        egui::pos2(x + bar_width, rect.bottom()),
+                    ),
+                    0.0,
+                    Color32::GRAY,
+                );
+            }
+        }
+        if ui
+            .button("Export CSV")
+            .on_hover_text("Saves the frame times in the window to frame-times/")
+            .clicked()
+        {
+            ui_state.export_result = Some(self.fps_counter.export());
+        }
+        match &ui_state.export_result {
+            Some(Ok(path)) => {
+                ui.weak(format!("Saved {}", path.display()));
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+            }
+            None => {}
+        }
+    }
+
+    fn present_mode_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        ui.horizontal(|ui| {
+            ui.label("Present mode:");
+            egui::ComboBox::from_id_source("present_mode")
+                .selected_text(format!("{:?}", ui_state.present_mode))
+                .show_ui(ui, |ui| {
+                    for &mode in &ui_state.present_modes {
+                        if ui
+                            .selectable_label(ui_state.present_mode == mode, format!("{mode:?}"))
+                            .clicked()
+                        {
+                            self.send_event(UserEvent::SetPresentMode(mode));
+                        }
+                    }
+                });
+        });
+    }
+
+    fn adapter_ui(&self, ui: &mut egui::Ui, ui_state: &UiState) {
+        if ui
+            .button("Copy")
+            .on_hover_text("Copies the report below, to paste into bug reports")
+            .clicked()
+        {
+            ui.output_mut(|output| output.copied_text = ui_state.adapter_report.clone());
+        }
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(&ui_state.adapter_report).monospace());
+            });
+    }
+
+    /// MSAA for meshes, supersampling for everything else
+    fn anti_aliasing_ui(
+        &self,
+        ui: &mut egui::Ui,
+        ui_state: &mut UiState,
+        controller: &dyn Controller,
+    ) {
+        ui.horizontal(|ui| {
+            if controller.buffers().is_some() {
+                ui.label("MSAA:");
+                egui::ComboBox::from_id_source("msaa")
+                    .selected_text(format!("{}x", ui_state.msaa))
+                    .show_ui(ui, |ui| {
+                        for &count in &ui_state.sample_counts {
+                            if ui
+                                .selectable_label(ui_state.msaa == count, format!("{count}x"))
+                                .clicked()
+                            {
+                                self.send_event(UserEvent::SetMsaa(count));
+                            }
+                        }
+                    });
+            } else {
+                ui.label("Supersampling:");
+                egui::ComboBox::from_id_source("supersampling")
+                    .selected_text(format!("{0}×{0}", ui_state.supersampling))
+                    .show_ui(ui, |ui| {
+                        for factor in 1..=4 {
+                            if ui
+                                .selectable_label(
+                                    ui_state.supersampling == factor,
+                                    format!("{factor}×{factor}"),
+                                )
+                                .clicked()
+                            {
+                                self.send_event(UserEvent::SetSupersampling(factor));
+                            }
+                        }
+                    });
+            }
+        });
+    }
+
+    fn render_scale_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        ui.horizontal(|ui| {
+            ui.label("Render scale:");
+            let mut percent = ui_state.render_scale;
+            if ui
+                .add(egui::Slider::new(&mut percent, 25..=200).suffix("%"))
+                .changed()
+            {
+                self.send_event(UserEvent::SetRenderScale(percent));
+            }
+        });
+        ui.horizontal(|ui| {
+            for upscaling in Upscaling::iter() {
+                if ui
+                    .radio(ui_state.upscaling == upscaling, upscaling.to_string())
+                    .clicked()
+                {
+                    self.send_event(UserEvent::SetUpscaling(upscaling));
+                }
+            }
+        });
+    }
+
+    fn accumulation_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut ui_state.accumulate, "Accumulate")
+                .clicked()
+            {
+                self.send_event(UserEvent::SetAccumulate(ui_state.accumulate));
+            }
+            if ui_state.accumulate {
+                ui.label(format!("{} frames", ui_state.accumulated_frames));
+            }
+        });
+    }
+
+    fn tonemapping_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        let mut tonemapping = ui_state.tonemapping;
+        ui.horizontal(|ui| {
+            ui.label("Tonemapping:");
+            egui::ComboBox::from_id_source("tonemapper")
+                .selected_text(tonemapping.tonemapper.to_string())
+                .show_ui(ui, |ui| {
+                    for tonemapper in Tonemapper::iter() {
+                        ui.selectable_value(
+                            &mut tonemapping.tonemapper,
+                            tonemapper,
+                            tonemapper.to_string(),
+                        );
+                    }
                 });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Exposure:");
+            ui.add(
+                egui::DragValue::new(&mut tonemapping.exposure)
+                    .clamp_range(-10.0..=10.0)
+                    .speed(0.05)
+                    .suffix(" EV"),
+            );
+        });
+        if tonemapping != ui_state.tonemapping {
+            self.send_event(UserEvent::SetTonemapping(tonemapping));
+        }
+    }
+
+    fn timeline_ui(&self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("Back one frame").clicked() {
+                self.send_event(UserEvent::StepFrames(-1));
+            }
+            let play_pause = if ui_state.paused { "▶" } else { "⏸" };
+            if ui.button(play_pause).clicked() {
+                self.send_event(UserEvent::SetPaused(!ui_state.paused));
+            }
+            if ui.button("⏭").on_hover_text("Forward one frame").clicked() {
+                self.send_event(UserEvent::StepFrames(1));
+            }
+            // Grow the timeline in 30 second chunks so the slider doesn't
+            // rescale every frame
+            let end = 30.0 * (ui_state.time / 30.0 + 1.0).floor();
+            let mut time = ui_state.time;
+            if ui
+                .add(egui::Slider::new(&mut time, 0.0..=end).suffix(" s"))
+                .changed()
+            {
+                self.send_event(UserEvent::SeekTime(time));
+            }
+            ui.label("Speed:");
+            let mut speed = ui_state.speed;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut speed)
+                        .clamp_range(0.0..=10.0)
+                        .speed(0.01)
+                        .suffix("x"),
+                )
+                .changed()
+            {
+                self.send_event(UserEvent::SetSpeed(speed));
+            }
+        });
+    }
+
+    fn presets_ui(
+        &self,
+        ui: &mut egui::Ui,
+        ui_state: &mut UiState,
+        controller: &mut dyn Controller,
+    ) {
+        let shader = ui_state.active_shader;
+        if ui_state.presets_shader != Some(shader) {
+            ui_state.presets = presets::list(shader);
+            ui_state.presets_shader = Some(shader);
+            ui_state.preset_error = None;
+        }
+        for name in &ui_state.presets {
+            if ui.button(name).clicked() {
+                ui_state.preset_error = presets::load(shader, name, controller).err();
+                if ui_state.preset_error.is_none() && controller.buffers().is_some() {
+                    self.send_event(UserEvent::NewVerticesReady);
+                }
+                ui_state.preset_name = name.clone();
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut ui_state.preset_name).desired_width(80.0));
+            if ui
+                .add_enabled(!ui_state.preset_name.is_empty(), egui::Button::new("Save"))
+                .clicked()
+            {
+                ui_state.preset_error =
+                    presets::save(shader, &ui_state.preset_name, &*controller).err();
+                // Re-read the directory so the new preset shows up
+                ui_state.presets_shader = None;
+            }
+        });
+        if let Some(err) = &ui_state.preset_error {
+            ui.colored_label(Color32::RED, err);
         }
     }
 }
@@ -1,4 +1,10 @@
-use crate::{shader::CompiledShaderModules, RustGPUShader};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::diagnostics::Diagnostic;
+use crate::{
+    blit::{Tonemapping, Upscaling},
+    shader::CompiledShaderModules,
+    RustGPUShader,
+};
 use winit::{
     dpi::PhysicalSize,
     event_loop::{EventLoop, EventLoopBuilder},
@@ -6,10 +12,26 @@ use winit::{
 };
 
 pub enum UserEvent {
+    CompileStarted(RustGPUShader),
     NewModule(RustGPUShader, CompiledShaderModules),
+    #[cfg(not(target_arch = "wasm32"))]
+    CompileFailed(RustGPUShader, Vec<Diagnostic>),
+    /// Changes to the shader sources may go unnoticed
+    WatchFailed(String),
     SwitchShader(RustGPUShader),
     NewVerticesReady,
-    SetVSync(bool),
+    NewTexturesReady,
+    SetPresentMode(wgpu::PresentMode),
+    SetMsaa(u32),
+    SetSupersampling(u32),
+    SetRenderScale(u32),
+    SetUpscaling(Upscaling),
+    SetTonemapping(Tonemapping),
+    SetAccumulate(bool),
+    SetPaused(bool),
+    SetSpeed(f32),
+    SeekTime(f32),
+    StepFrames(i32),
 }
 
 pub struct Window {
@@ -0,0 +1,248 @@
+//! `#[derive(ParamsUi)]` generates the egui rows for the tunable fields of a
+//! controller, so they don't have to be written out by hand.
+//!
+//! ```ignore
+//! #[derive(ParamsUi)]
+//! pub struct Controller {
+//!     cursor: Vec2,
+//!     #[param(range = 1.0..=6.0, speed = 0.1)]
+//!     exponent: f32,
+//!     #[param(label = "l", range = 0..=self.n - 1)]
+//!     l: i32,
+//!     #[param]
+//!     include_time_factor: bool,
+//!     #[param(radio)]
+//!     shape: Shape,
+//! }
+//! ```
+//!
+//! Only fields marked `#[param]` are shown, in declaration order, by the
+//! generated `ParamsUi::params_ui`, which returns whether any of them
+//! changed. A controller returning the struct from `Controller::params` gets
+//! its `ui` and `has_ui` from it. Numbers get a `DragValue`, `bool`s a
+//! checkbox, and `radio` fields a radio button for each variant of a
+//! `strum::EnumIter` enum. `range` is evaluated before the field is borrowed,
+//! so it may depend on other fields.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, Ident, LitStr, Type};
+
+#[proc_macro_derive(ParamsUi, attributes(param))]
+pub fn derive_params_ui(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ParamsUi can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ParamsUi needs named fields",
+        ));
+    };
+
+    let mut rows = vec![];
+    for field in &fields.named {
+        let Some(attr) = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("param"))
+        else {
+            continue;
+        };
+        let param = Param::parse(attr)?;
+        rows.push(param.row(field.ident.as_ref().unwrap(), &field.ty)?);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics crate::controller::ParamsUi for #name #ty_generics #where_clause {
+            fn params_ui(&mut self, ui: &mut ::egui::Ui) -> bool {
+                let mut changed = false;
+                #(#rows)*
+                changed
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct Param {
+    range: Option<Expr>,
+    speed: Option<Expr>,
+    label: Option<LitStr>,
+    radio: bool,
+}
+
+impl Param {
+    fn parse(attr: &Attribute) -> syn::Result<Self> {
+        let mut param = Self::default();
+        // A bare `#[param]` takes the defaults
+        if let syn::Meta::Path(_) = attr.meta {
+            return Ok(param);
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("range") {
+                param.range = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("speed") {
+                param.speed = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("label") {
+                param.label = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("radio") {
+                param.radio = true;
+            } else {
+                return Err(meta.error("expected `range`, `speed`, `label` or `radio`"));
+            }
+            Ok(())
+        })?;
+        if param.radio && (param.range.is_some() || param.speed.is_some()) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`radio` can't be combined with `range` or `speed`",
+            ));
+        }
+        Ok(param)
+    }
+
+    fn row(self, ident: &Ident, ty: &Type) -> syn::Result<TokenStream2> {
+        if self.radio {
+            // The variant names already say what the field is, so only show a
+            // heading when one is asked for
+            let heading = self.label.map(|label| quote!(ui.label(#label);));
+            return Ok(quote! {
+                #heading
+                for value in <#ty as ::strum::IntoEnumIterator>::iter() {
+                    changed |= ui.radio_value(&mut self.#ident, value, value.to_string()).changed();
+                }
+            });
+        }
+
+        let label = self
+            .label
+            .map_or_else(|| title_case(&ident.to_string()), |label| label.value());
+        if is_bool(ty) {
+            return Ok(quote! {
+                changed |= ui.checkbox(&mut self.#ident, #label).changed();
+            });
+        }
+
+        let label = format!("{label}:");
+        let (range, clamp_range) = match self.range {
+            Some(range) => (quote!(let range = #range;), quote!(.clamp_range(range))),
+            None => (quote!(), quote!()),
+        };
+        let speed = self.speed.map(|speed| quote!(.speed(#speed)));
+        Ok(quote! {
+            {
+                #range
+                changed |= ui
+                    .horizontal(|ui| {
+                        ui.label(#label);
+                        ui.add(::egui::DragValue::new(&mut self.#ident) #clamp_range #speed)
+                            .changed()
+                    })
+                    .inner;
+            }
+        })
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("bool"))
+}
+
+/// `num_iterations` -> `Num Iterations`
+fn title_case(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    fn parse(attr: Attribute) -> syn::Result<Param> {
+        Param::parse(&attr)
+    }
+
+    #[test]
+    fn test_title_case() {
+        assert_eq!(title_case("num_iterations"), "Num Iterations");
+        assert_eq!(title_case("l"), "L");
+        assert_eq!(title_case("_private__name_"), "Private Name");
+    }
+
+    #[test]
+    fn test_parse_bare() {
+        let param = parse(parse_quote!(#[param])).unwrap();
+        assert!(param.range.is_none() && param.speed.is_none() && param.label.is_none());
+        assert!(!param.radio);
+    }
+
+    #[test]
+    fn test_parse() {
+        let param = parse(parse_quote!(#[param(label = "l", range = 0..=self.n - 1, speed = 0.1)]))
+            .unwrap();
+        assert_eq!(param.label.unwrap().value(), "l");
+        assert_eq!(
+            param.range.map(|range| quote!(#range).to_string()),
+            Some(quote!(0..=self.n - 1).to_string())
+        );
+        assert_eq!(
+            param.speed.map(|speed| quote!(#speed).to_string()),
+            Some("0.1".to_string())
+        );
+
+        let param = parse(parse_quote!(#[param(radio, label = "Shape")])).unwrap();
+        assert!(param.radio);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(parse_quote!(#[param(step = 1)])).is_err());
+        assert!(parse(parse_quote!(#[param(radio, range = 0..=1)])).is_err());
+        assert!(parse(parse_quote!(#[param(range)])).is_err());
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert!(expand(parse_quote!(
+            enum Shape {
+                Circle,
+            }
+        ))
+        .is_err());
+        assert!(expand(parse_quote!(
+            struct State(f32);
+        ))
+        .is_err());
+        assert!(expand(parse_quote!(
+            struct State {
+                #[param(label = 1)]
+                zoom: f32,
+            }
+        ))
+        .is_err());
+    }
+}
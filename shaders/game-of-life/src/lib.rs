@@ -0,0 +1,102 @@
+#![cfg_attr(target_arch = "spirv", no_std)]
+
+use push_constants::game_of_life::{ShaderConstants, CELL_SIZE};
+use shared::*;
+use spirv_std::glam::{uvec2, vec3, UVec2, UVec3, Vec2, Vec4, Vec4Swizzles};
+use spirv_std::spirv;
+
+/// Cells within this many cells of the cursor come alive while drawing
+const BRUSH_RADIUS: f32 = 2.5;
+/// Cells hold the number of steps they have been alive, up to this
+const MAX_AGE: u32 = 1000;
+
+fn age(cells: &[u32], grid: UVec2, x: i32, y: i32) -> u32 {
+    // The edges wrap around
+    let x = (x + grid.x as i32) as u32 % grid.x;
+    let y = (y + grid.y as i32) as u32 % grid.y;
+    cells[(y * grid.x + x) as usize]
+}
+
+fn step(id: UVec2, constants: &ShaderConstants, input: &[u32], output: &mut [u32]) {
+    let grid = uvec2(constants.grid.width, constants.grid.height);
+    let (x, y) = (id.x as i32, id.y as i32);
+    let mut neighbors = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if (dx != 0 || dy != 0) && age(input, grid, x + dx, y + dy) > 0 {
+                neighbors += 1;
+            }
+        }
+    }
+    let age = age(input, grid, x, y);
+    let alive = neighbors == 3 || (neighbors == 2 && age > 0);
+    output[(id.y * grid.x + id.x) as usize] = if alive { (age + 1).min(MAX_AGE) } else { 0 };
+}
+
+fn draw(id: UVec2, constants: &ShaderConstants, cells: &mut [u32]) {
+    let cursor: Vec2 = constants.cursor.into();
+    if constants.drawing != 0 && (id.as_vec2() + 0.5).distance(cursor) < BRUSH_RADIUS {
+        let index = (id.y * constants.grid.width + id.x) as usize;
+        cells[index] = cells[index].max(1);
+    }
+}
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main_cs(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] cells_0: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] cells_1: &mut [u32],
+) {
+    if id.x >= constants.grid.width || id.y >= constants.grid.height {
+        return;
+    }
+    let id = uvec2(id.x, id.y);
+    // A step reads the buffer the previous step wrote, and writes the other
+    if constants.parity == 0 {
+        if constants.advance != 0 {
+            step(id, constants, cells_1, cells_0);
+        }
+        draw(id, constants, cells_0);
+    } else {
+        if constants.advance != 0 {
+            step(id, constants, cells_0, cells_1);
+        }
+        draw(id, constants, cells_1);
+    }
+}
+
+#[spirv(fragment)]
+pub fn main_fs(
+    #[spirv(frag_coord)] frag_coord: Vec4,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] cells_0: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] cells_1: &[u32],
+    output: &mut Vec4,
+) {
+    let cell = (frag_coord.xy() / CELL_SIZE as f32).as_uvec2();
+    let index = (cell.y * constants.grid.width + cell.x) as usize;
+    let age = if constants.parity == 0 {
+        cells_0[index]
+    } else {
+        cells_1[index]
+    };
+
+    let col = if age == 0 {
+        vec3(0.02, 0.02, 0.03)
+    } else {
+        // Newborn cells are bright and fade as they get older
+        let t = saturate(age as f32 / 64.0);
+        vec3(1.0, 0.85, 0.35).lerp(vec3(0.1, 0.35, 0.7), t)
+    };
+
+    *output = col.extend(1.0);
+}
+
+#[spirv(vertex)]
+pub fn main_vs(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(position, invariant)] out_pos: &mut Vec4,
+) {
+    fullscreen_vs(vert_id, out_pos)
+}
@@ -48,6 +48,6 @@ pub fn main_vs(
 }
 
 fn from_pixels(x: f32, y: f32, constants: &ShaderConstants) -> Vec2 {
-    (vec2(x, -y) - 0.5 * vec2(constants.width as f32, -(constants.height as f32)))
-        / constants.height as f32
+    (vec2(x, -y) - 0.5 * vec2(constants.size.width as f32, -(constants.size.height as f32)))
+        / constants.size.height as f32
 }
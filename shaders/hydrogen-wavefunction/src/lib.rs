@@ -61,7 +61,8 @@ pub fn main_fs(
 ) {
     let translate = vec2(constants.translate_x, constants.translate_y) / constants.height as f32;
 
-    let uv = (Complex::from(frag_coord.xy())
+    let jitter: Vec2 = constants.jitter.into();
+    let uv = (Complex::from(frag_coord.xy() + jitter)
         - 0.5 * Complex::new(constants.width as f32, constants.height as f32))
         / constants.height as f32;
 
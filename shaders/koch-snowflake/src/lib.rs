@@ -54,7 +54,8 @@ pub fn main_fs(
     #[spirv(push_constant)] constants: &ShaderConstants,
     output: &mut Vec4,
 ) {
-    let uv = from_pixels(frag_coord.x, frag_coord.y, constants);
+    let uv = from_pixels(frag_coord.x, frag_coord.y, constants) * constants.zoom
+        + vec2(constants.translate_x, constants.translate_y);
     let cursor = from_pixels(constants.cursor_x, constants.cursor_y, constants);
 
     let d = {
@@ -67,7 +68,11 @@ pub fn main_fs(
         }
     };
 
-    let col = Vec3::splat(smoothstep(1.0 / (constants.height as f32), 0.0, d.abs()));
+    let col = Vec3::splat(smoothstep(
+        constants.zoom / (constants.height as f32),
+        0.0,
+        d.abs(),
+    ));
 
     *output = col.extend(1.0);
 }
@@ -0,0 +1,50 @@
+#![cfg_attr(target_arch = "spirv", no_std)]
+
+use push_constants::lens::ShaderConstants;
+use shared::*;
+use spirv_std::glam::{vec2, Vec2, Vec3, Vec4, Vec4Swizzles};
+use spirv_std::{image::Image2d, spirv, Sampler};
+
+#[spirv(fragment)]
+pub fn main_fs(
+    #[spirv(frag_coord)] frag_coord: Vec4,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 1, binding = 0)] image: &Image2d,
+    #[spirv(descriptor_set = 1, binding = 1)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    let uv = from_pixels(frag_coord.xy(), constants.size);
+    let cursor = from_pixels(constants.cursor.into(), constants.size);
+    let pixel = 1.0 / constants.size.height as f32;
+
+    // Magnified most at the center of the lens, and not at all at its rim
+    let offset = uv - cursor;
+    let t = saturate(offset.length() / constants.radius);
+    let magnification = constants.magnification + (1.0 - constants.magnification) * t * t;
+    let uv = cursor + offset / magnification;
+
+    // The image is fitted to the window, with a margin
+    let image_aspect = constants.image_size.aspect_ratio();
+    let scale = 0.9 * (constants.size.aspect_ratio() / image_aspect).min(1.0);
+    let st = vec2(uv.x / (image_aspect * scale), -uv.y / scale) + 0.5;
+    // Sampled outside the branch, as implicit derivatives need uniform control flow
+    let color: Vec4 = image.sample(*sampler, st);
+    let mut col = if st.cmpge(Vec2::ZERO).all() && st.cmple(Vec2::ONE).all() {
+        color.xyz()
+    } else {
+        Vec3::splat(0.02)
+    };
+
+    let rim = (offset.length() - constants.radius).abs();
+    col *= 1.0 - 0.8 * smoothstep(2.0 * pixel, 0.0, rim);
+
+    *output = col.extend(1.0);
+}
+
+#[spirv(vertex)]
+pub fn main_vs(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(position, invariant)] out_pos: &mut Vec4,
+) {
+    fullscreen_vs(vert_id, out_pos)
+}
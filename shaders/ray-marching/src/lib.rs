@@ -3,7 +3,7 @@
 use push_constants::ray_marching::ShaderConstants;
 use shared::sdf_3d as sdf;
 use shared::*;
-use spirv_std::glam::{vec2, vec3, Mat3, Vec2Swizzles, Vec3, Vec4};
+use spirv_std::glam::{vec2, vec3, Mat3, Vec2, Vec2Swizzles, Vec3, Vec4, Vec4Swizzles};
 #[cfg_attr(not(target_arch = "spirv"), allow(unused_imports))]
 use spirv_std::num_traits::Float;
 use spirv_std::spirv;
@@ -94,12 +94,15 @@ pub fn main_fs(
     ) / constants.height as f32
         * PI;
 
-    let uv = (vec2(frag_coord.x, -frag_coord.y)
+    let jitter: Vec2 = constants.jitter.into();
+    let coord = frag_coord.xy() + jitter;
+    let uv = (vec2(coord.x, -coord.y)
         - 0.5 * vec2(constants.width as f32, -(constants.height as f32)))
         / constants.height as f32;
 
     let rm = Mat3::from_rotation_y(translate.x).mul_mat3(&Mat3::from_rotation_x(translate.y));
-    let ro = rm.mul_vec3(vec3(0.0, 1.0, -constants.zoom));
+    let position: Vec3 = constants.position.into();
+    let ro = rm.mul_vec3(vec3(0.0, 1.0, -constants.zoom)) + position;
     let rd = rm.mul_vec3(vec3(uv.x, uv.y, 1.0)).normalize();
 
     let (d, cd) = ray_march(ro, rd, constants.time);
@@ -41,8 +41,9 @@ pub fn main_fs(
     #[spirv(push_constant)] constants: &ShaderConstants,
     output: &mut Vec4,
 ) {
-    let uv = from_pixels(frag_coord.xy(), constants.size);
-    let cursor = from_pixels(constants.cursor.into(), constants.size);
+    let translate: Vec2 = constants.translate.into();
+    let uv = from_pixels(frag_coord.xy(), constants.size) * constants.zoom + translate;
+    let cursor = from_pixels(constants.cursor.into(), constants.size) * constants.zoom + translate;
 
     let col = {
         let d = sdf(uv, constants.shape, constants.params);
@@ -59,7 +60,7 @@ pub fn main_fs(
 
         if constants.mouse_button_pressed & 1 != 0 {
             let d = sdf(cursor, constants.shape, constants.params);
-            let thickness = 1.0 / constants.size.height as f32;
+            let thickness = constants.zoom / constants.size.height as f32;
             col = col
                 .lerp(
                     vec3(1.0, 1.0, 0.0),
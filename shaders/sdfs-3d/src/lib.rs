@@ -140,10 +140,12 @@ pub fn main_fs(
     let translate: Vec2 = constants.translate.into();
     let cursor: Vec3 = constants.cursor.into();
 
-    let uv = from_pixels(frag_coord.xy(), constants.size);
+    let jitter: Vec2 = constants.jitter.into();
+    let uv = from_pixels(frag_coord.xy() + jitter, constants.size);
 
     let rm = Mat3::from_rotation_y(translate.x).mul_mat3(&Mat3::from_rotation_x(translate.y));
-    let ro = rm.mul_vec3(-Vec3::Z);
+    let position: Vec3 = constants.position.into();
+    let ro = rm.mul_vec3(-Vec3::Z) + position;
     let rd = rm.mul_vec3(uv.extend(1.0)).normalize();
 
     let slice_z = constants.slice_z;
@@ -3,8 +3,10 @@ use spirv_std::glam;
 #[cfg(not(target_arch = "spirv"))]
 use winit::dpi::PhysicalSize;
 
+pub mod game_of_life;
 pub mod hydrogen_wavefunction;
 pub mod koch_snowflake;
+pub mod lens;
 pub mod mandelbrot;
 pub mod ray_marching;
 pub mod ray_marching_2d;
@@ -13,6 +15,8 @@ pub mod sdfs_3d;
 pub mod sierpinski_triangle;
 pub mod spherical_harmonics;
 pub mod spherical_harmonics_shape;
+pub mod trails;
+pub mod voronoi;
 
 pub fn largest_size() -> usize {
     use core::mem::size_of;
@@ -26,6 +30,10 @@ pub fn largest_size() -> usize {
         .max(size_of::<hydrogen_wavefunction::ShaderConstants>())
         .max(size_of::<spherical_harmonics::ShaderConstants>())
         .max(size_of::<spherical_harmonics_shape::ShaderConstants>())
+        .max(size_of::<voronoi::ShaderConstants>())
+        .max(size_of::<lens::ShaderConstants>())
+        .max(size_of::<game_of_life::ShaderConstants>())
+        .max(size_of::<trails::ShaderConstants>())
 }
 
 #[derive(Copy, Clone, Pod, Zeroable)]
@@ -48,6 +56,10 @@ impl From<PhysicalSize<u32>> for Size {
     }
 }
 
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
 pub struct Vec2 {
@@ -71,6 +83,10 @@ impl Into<glam::Vec2> for Vec2 {
     }
 }
 
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
 pub struct Vec3 {
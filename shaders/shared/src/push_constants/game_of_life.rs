@@ -0,0 +1,20 @@
+use super::{Size, Vec2};
+use bytemuck::{Pod, Zeroable};
+
+/// Width and height of a cell in pixels
+pub const CELL_SIZE: u32 = 4;
+
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ShaderConstants {
+    pub size: Size,
+    /// Columns and rows of cells
+    pub grid: Size,
+    /// Cursor in cells
+    pub cursor: Vec2,
+    pub drawing: u32,
+    /// Whether to take a step, rather than only draw into the current cells
+    pub advance: u32,
+    /// Index of the buffer holding the current cells
+    pub parity: u32,
+}
@@ -1,3 +1,4 @@
+use super::Vec2;
 use bytemuck::{Pod, Zeroable};
 
 #[derive(Copy, Clone, Pod, Zeroable)]
@@ -17,4 +18,6 @@ pub struct ShaderConstants {
     pub l: u32,
     pub m: i32,
     pub root: i32,
+    /// Subpixel offset of `frag_coord` while the runner accumulates frames
+    pub jitter: Vec2,
 }
@@ -8,4 +8,7 @@ pub struct ShaderConstants {
     pub cursor_x: f32,
     pub cursor_y: f32,
     pub use_antisnowflake: u32,
+    pub zoom: f32,
+    pub translate_x: f32,
+    pub translate_y: f32,
 }
@@ -0,0 +1,12 @@
+use super::{Size, Vec2};
+use bytemuck::{Pod, Zeroable};
+
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ShaderConstants {
+    pub size: Size,
+    pub image_size: Size,
+    pub cursor: Vec2,
+    pub radius: f32,
+    pub magnification: f32,
+}
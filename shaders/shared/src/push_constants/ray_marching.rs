@@ -1,3 +1,4 @@
+use super::{Vec2, Vec3};
 use bytemuck::{Pod, Zeroable};
 
 #[derive(Copy, Clone, Pod, Zeroable)]
@@ -19,4 +20,10 @@ pub struct ShaderConstants {
 
     /// Bit mask of the pressed buttons (0 = Left, 1 = Middle, 2 = Right).
     pub mouse_button_pressed: u32,
+
+    /// Offset of the camera from its orbit, moved with WASD/QE
+    pub position: Vec3,
+
+    /// Subpixel offset of `frag_coord` while the runner accumulates frames
+    pub jitter: Vec2,
 }
@@ -1,7 +1,10 @@
 use super::{vec2, Size, Vec2};
 use bytemuck::{Pod, Zeroable};
 
-#[cfg_attr(not(target_arch = "spirv"), derive(strum::EnumIter, strum::Display))]
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(strum::EnumIter, strum::Display, serde::Serialize, serde::Deserialize)
+)]
 #[derive(PartialEq, Copy, Clone)]
 #[repr(u32)]
 pub enum Shape {
@@ -120,6 +123,10 @@ pub struct ShapeSpec {
     pub is_radial: bool,
 }
 
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
 pub struct Params {
@@ -141,4 +148,7 @@ pub struct ShaderConstants {
 
     pub shape: u32,
     pub params: Params,
+
+    pub zoom: f32,
+    pub translate: Vec2,
 }
@@ -1,7 +1,10 @@
 use super::{vec3, Size, Vec2, Vec3};
 use bytemuck::{Pod, Zeroable};
 
-#[cfg_attr(not(target_arch = "spirv"), derive(strum::EnumIter, strum::Display))]
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(strum::EnumIter, strum::Display, serde::Serialize, serde::Deserialize)
+)]
 #[derive(PartialEq, Copy, Clone)]
 #[repr(u32)]
 pub enum Shape {
@@ -76,6 +79,10 @@ pub struct ShapeSpec {
     pub is_radial: bool,
 }
 
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
 pub struct Params {
@@ -93,6 +100,8 @@ pub struct ShaderConstants {
     pub cursor: Vec3,
     pub slice_z: f32,
     pub translate: Vec2,
+    /// Offset of the camera from its orbit, moved with WASD/QE
+    pub position: Vec3,
 
     /// Bit mask of the pressed buttons (0 = Left, 1 = Middle, 2 = Right).
     pub mouse_button_pressed: u32,
@@ -100,6 +109,8 @@ pub struct ShaderConstants {
     // pub rotation: f32,
     pub shape: u32,
     pub params: Params,
+    /// Subpixel offset of `frag_coord` while the runner accumulates frames
+    pub jitter: Vec2,
 }
 
 pub fn sdf_shape(p: spirv_std::glam::Vec3, shape: Shape, params: Params) -> f32 {
@@ -1,6 +1,10 @@
 use crate::push_constants::{Quat, Size, Vec2};
 use bytemuck::{Pod, Zeroable};
 
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(strum::EnumIter, strum::Display, serde::Serialize, serde::Deserialize)
+)]
 #[derive(PartialEq, Copy, Clone)]
 #[repr(u32)]
 pub enum Variant {
@@ -1,7 +1,10 @@
 use super::{Mat4, Quat};
 use bytemuck::{Pod, Zeroable};
 
-#[cfg_attr(not(target_arch = "spirv"), derive(strum::EnumIter, strum::Display))]
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(strum::EnumIter, strum::Display, serde::Serialize, serde::Deserialize)
+)]
 #[derive(PartialEq, Copy, Clone)]
 #[repr(u32)]
 pub enum Variant {
@@ -0,0 +1,14 @@
+use super::{Size, Vec2};
+use bytemuck::{Pod, Zeroable};
+
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ShaderConstants {
+    pub size: Size,
+    pub time: f32,
+    pub cursor: Vec2,
+    pub drawing: u32,
+    /// Factor the trails fade by since the last frame
+    pub decay: f32,
+    pub num_points: u32,
+}
@@ -0,0 +1,19 @@
+use super::Size;
+use bytemuck::{Pod, Zeroable};
+
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ShaderConstants {
+    pub size: Size,
+    /// Index of the site under the cursor, or `u32::MAX`
+    pub hovered: u32,
+}
+
+/// Bound as a uniform buffer next to the storage buffer of sites
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct Style {
+    pub border_width: f32,
+    pub site_radius: f32,
+    pub show_distance: u32,
+}
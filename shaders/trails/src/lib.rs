@@ -0,0 +1,77 @@
+#![cfg_attr(target_arch = "spirv", no_std)]
+
+use push_constants::trails::ShaderConstants;
+use shared::*;
+use spirv_std::glam::{vec2, vec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+#[cfg_attr(not(target_arch = "spirv"), allow(unused_imports))]
+use spirv_std::num_traits::Float;
+use spirv_std::{image::Image2d, spirv, Sampler};
+
+const RADIUS: f32 = 0.012;
+
+/// Point `i` traces a Lissajous curve with its own frequencies
+fn point(i: u32, time: f32) -> Vec2 {
+    let i = i as f32;
+    vec2(
+        0.7 * (time * (0.5 + 0.13 * i) + i).sin(),
+        0.4 * (time * (0.7 + 0.07 * i) + 2.0 * i).cos(),
+    )
+}
+
+fn color(i: u32) -> Vec3 {
+    let hue = 2.0 * PI * (i as f32 * 0.618034).fract();
+    let phase = 2.0 * PI / 3.0;
+    0.55 + 0.45 * vec3(hue.cos(), (hue - phase).cos(), (hue + phase).cos())
+}
+
+fn disk(uv: Vec2, center: Vec2, pixel: f32) -> f32 {
+    smoothstep(RADIUS + pixel, RADIUS, uv.distance(center))
+}
+
+/// Fades the previous frame's trails and draws the points on top
+#[spirv(fragment)]
+pub fn main_fs_a(
+    #[spirv(frag_coord)] frag_coord: Vec4,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 2, binding = 0)] buffer_a: &Image2d,
+    #[spirv(descriptor_set = 2, binding = 1)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    let size = vec2(constants.size.width as f32, constants.size.height as f32);
+    let uv = from_pixels(frag_coord.xy(), constants.size);
+    let pixel = 1.0 / size.y;
+
+    let previous: Vec4 = buffer_a.sample(*sampler, frag_coord.xy() / size);
+    // The maximum rather than the sum, so that points don't pile up while paused
+    let mut col = previous.xyz() * constants.decay;
+    for i in 0..constants.num_points {
+        col = col.max(color(i) * disk(uv, point(i, constants.time), pixel));
+    }
+    if constants.drawing != 0 {
+        let cursor = from_pixels(constants.cursor.into(), constants.size);
+        col = col.max(Vec3::splat(disk(uv, cursor, pixel)));
+    }
+
+    *output = col.extend(1.0);
+}
+
+#[spirv(fragment)]
+pub fn main_fs(
+    #[spirv(frag_coord)] frag_coord: Vec4,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 2, binding = 0)] buffer_a: &Image2d,
+    #[spirv(descriptor_set = 2, binding = 1)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    let size = vec2(constants.size.width as f32, constants.size.height as f32);
+    let trails: Vec4 = buffer_a.sample(*sampler, frag_coord.xy() / size);
+    *output = trails.xyz().extend(1.0);
+}
+
+#[spirv(vertex)]
+pub fn main_vs(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(position, invariant)] out_pos: &mut Vec4,
+) {
+    fullscreen_vs(vert_id, out_pos)
+}
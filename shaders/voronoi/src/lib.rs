@@ -0,0 +1,74 @@
+#![cfg_attr(target_arch = "spirv", no_std)]
+
+use push_constants::voronoi::{ShaderConstants, Style};
+use shared::*;
+use spirv_std::glam::{vec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+#[cfg_attr(not(target_arch = "spirv"), allow(unused_imports))]
+use spirv_std::num_traits::Float;
+use spirv_std::spirv;
+
+/// A color per site that doesn't change when later sites are added or removed
+fn site_color(i: u32) -> Vec3 {
+    // Successive hues are spread by the golden ratio
+    let hue = 2.0 * PI * (i as f32 * 0.618034).fract();
+    let phase = 2.0 * PI / 3.0;
+    0.5 + 0.35 * vec3(hue.cos(), (hue - phase).cos(), (hue + phase).cos())
+}
+
+#[spirv(fragment)]
+pub fn main_fs(
+    #[spirv(frag_coord)] frag_coord: Vec4,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] style: &Style,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] sites: &[Vec2],
+    output: &mut Vec4,
+) {
+    if sites.is_empty() {
+        *output = Vec4::W;
+        return;
+    }
+    let uv = from_pixels(frag_coord.xy(), constants.size);
+    let pixel = 1.0 / constants.size.height as f32;
+
+    let mut nearest = 0;
+    let mut d1 = f32::INFINITY;
+    let mut d2 = f32::INFINITY;
+    // Slice iterators don't compile to SPIR-V
+    let mut i = 0;
+    while i < sites.len() {
+        let d = uv.distance(sites[i]);
+        if d < d1 {
+            d2 = d1;
+            d1 = d;
+            nearest = i as u32;
+        } else if d < d2 {
+            d2 = d;
+        }
+        i += 1;
+    }
+
+    let mut col = site_color(nearest);
+    if style.show_distance != 0 {
+        col *= 1.0 - 0.7 * saturate(4.0 * d1);
+    }
+    // Half the difference of the distances to the two nearest sites
+    // approximates the distance to the edge between their cells
+    let edge = 0.5 * (d2 - d1);
+    col *= smoothstep(style.border_width, style.border_width + pixel, edge);
+    let radius = if nearest == constants.hovered {
+        1.5 * style.site_radius
+    } else {
+        style.site_radius
+    };
+    col = col.lerp(Vec3::ONE, smoothstep(radius + pixel, radius, d1));
+
+    *output = col.extend(1.0);
+}
+
+#[spirv(vertex)]
+pub fn main_vs(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(position, invariant)] out_pos: &mut Vec4,
+) {
+    fullscreen_vs(vert_id, out_pos)
+}